@@ -1,5 +1,8 @@
 
-use egui::{pos2, vec2, Color32, Rect, Stroke, TextureHandle, Ui};
+use std::cmp::Reverse;
+
+use egui::{pos2, vec2, Color32, Mesh, Rect, Stroke, TextureHandle, Ui};
+use itertools::Itertools;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -33,10 +36,103 @@ pub struct OAM {
     pub y: i8,
     pub palette: usize,
     pub tile: usize,
+    // Affine OAMs use a matrix instead of flipping
+    #[serde(default)]
+    pub affine: bool,
+    // Only for affine OAMs, a normal OAM with this bit set is hidden
+    #[serde(default)]
+    pub double_size: bool,
+    #[serde(default)]
+    pub affine_matrix: usize,
+    #[serde(default)]
+    pub mode: OAMMode,
+    #[serde(default)]
+    pub mosaic: bool,
+    // 0 is drawn in front of everything
+    #[serde(default)]
+    pub priority: u8,
+    // Editor only, these don't get exported
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub locked: bool,
+    // Exported as a comment next to the OAM
+    #[serde(default)]
+    pub label: String,
     #[serde(skip)]
     pub selected: bool
 }
 
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum OAMMode {
+    #[default]
+    Normal,
+    SemiTransparent,
+    // Not drawn, only used as a mask for the OBJ window
+    Window
+}
+
+// The blending and mosaic registers, shared by every OAM on screen
+#[derive(Clone)]
+pub struct ObjEffects {
+    pub eva: u8,
+    pub evb: u8,
+    pub mosaic_width: usize,
+    pub mosaic_height: usize,
+    pub obj_windows_visible: bool
+}
+
+impl ObjEffects {
+    pub fn init() -> Self {
+        Self {
+            eva: 8,
+            evb: 8,
+            mosaic_width: 1,
+            mosaic_height: 1,
+            obj_windows_visible: true
+        }
+    }
+}
+
+// A rotation/scale matrix that affine OAMs can point to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AffineMatrix {
+    // In degrees, counterclockwise
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32
+}
+
+impl AffineMatrix {
+    pub fn identity() -> Self {
+        Self {
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0
+        }
+    }
+
+    // How the sprite's pixels are moved on screen, [a, b, c, d] for [[a, b], [c, d]]
+    pub fn get_transform(&self) -> [f32; 4] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+
+        [cos * self.scale_x, sin * self.scale_y, -sin * self.scale_x, cos * self.scale_y]
+    }
+
+    // The GBA goes the other way, from screen to texture, with 8.8 fixed point PA, PB, PC and PD
+    pub fn get_parameters(&self) -> [i16; 4] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let to_fixed = |value: f32| (value * 256.0).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+
+        [
+            to_fixed(cos / self.scale_x),
+            to_fixed(-sin / self.scale_x),
+            to_fixed(sin / self.scale_y),
+            to_fixed(cos / self.scale_y)
+        ]
+    }
+}
+
 impl OAM {
     pub fn new(bytes: &[u8]) -> OAM {
         // 0xSYYY, 0xFXXX, 0xPTTT
@@ -46,14 +142,24 @@ impl OAM {
         
         // TODO: probably throw a warning if shape/size are invalid
         
-        let shape = match word1 >> 0xc {
+        let shape = match word1 >> 0xe {
             0x0 => OAMShape::Square,
-            0x4 => OAMShape::Horizontal,
-            0x8 => OAMShape::Vertical,
+            0x1 => OAMShape::Horizontal,
+            0x2 => OAMShape::Vertical,
             _ => OAMShape::Square
         };
 
-        let mut y= (word1 & 0x0FFF) as i16;
+        let affine = word1 & 0x0100 != 0;
+        let double_size = word1 & 0x0200 != 0;
+        let mosaic = word1 & 0x1000 != 0;
+
+        let mode = match (word1 >> 0xa) & 0x3 {
+            0x1 => OAMMode::SemiTransparent,
+            0x2 => OAMMode::Window,
+            _ => OAMMode::Normal
+        };
+
+        let mut y= (word1 & 0x00FF) as i16;
         if y >= 0x80 {
             y -= 0x100;
         }
@@ -67,22 +173,27 @@ impl OAM {
             _ => OAMSize::Size0
         };
 
+        // The flip bits are part of the matrix index on affine OAMs
         let flip = match flip_size_nibble - (flip_size_nibble & !0x3) {
+            _ if affine => OAMFlip::None,
             0x0 => OAMFlip::None,
             0x1 => OAMFlip::Horizontal,
             0x2 => OAMFlip::Vertical,
             0x3 => OAMFlip::Both,
             _ => OAMFlip::None
         };
+
+        let affine_matrix = if affine { ((word2 >> 9) & 0x1F) as usize } else { 0 };
         
-        let mut x = (word2 & 0x0FFF) as i16;
+        let mut x = (word2 & 0x01FF) as i16;
 
         if x >= 0x80 {
             x -= 0x200;
         }
         
         let palette = (word3 >> 0xc) as usize;
-        let tile = (word3 & 0x0FFF) as usize;
+        let priority = ((word3 >> 0xa) & 0x3) as u8;
+        let tile = (word3 & 0x03FF) as usize;
         
         OAM {
             shape,
@@ -92,6 +203,15 @@ impl OAM {
             y: y as i8,
             palette,
             tile,
+            affine,
+            double_size,
+            affine_matrix,
+            mode,
+            mosaic,
+            priority,
+            hidden: false,
+            locked: false,
+            label: String::new(),
             selected: false
         }
     }
@@ -125,7 +245,7 @@ impl OAM {
         let palette = bytes[5] as usize;
         let tile = (((bytes[6] as usize) << 8) | (bytes[7] as usize)) as usize;
 
-        OAM {shape, size, flip, x, y, palette, tile, selected: false}
+        OAM {shape, size, flip, x, y, palette, tile, affine: false, double_size: false, affine_matrix: 0, mode: OAMMode::Normal, mosaic: false, priority: 0, hidden: false, locked: false, label: String::new(), selected: false}
     }
     
     pub fn get_width_and_height(&self) -> (usize, usize) {
@@ -188,6 +308,24 @@ impl OAM {
         return sprite_indexes;
     }
 
+    // Finds which tile and which pixel inside it is shown at (x, y) of the OAM, taking flip into account
+    pub fn get_pixel_location(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let (width, height) = self.get_width_and_height();
+        if x >= width * 8 || y >= height * 8 {return None;}
+
+        let tile = self.get_sprite_indexes()[y / 8][x / 8];
+
+        let (mut pixel_x, mut pixel_y) = (x % 8, y % 8);
+        if matches!(self.flip, OAMFlip::Horizontal | OAMFlip::Both) {
+            pixel_x = 7 - pixel_x;
+        }
+        if matches!(self.flip, OAMFlip::Vertical | OAMFlip::Both) {
+            pixel_y = 7 - pixel_y;
+        }
+
+        Some((tile, pixel_x + pixel_y * 8))
+    }
+
     pub fn get_sprite_indexes_one_dimensional(&self) -> Vec<usize> {
         let two_dimensional_indexes = self.get_sprite_indexes();
         let mut indexes = Vec::new();
@@ -201,7 +339,127 @@ impl OAM {
         return indexes;
     }
 
-    pub fn draw(&self, textures: &Vec<Vec<TextureHandle>>, ui: &mut Ui, selection_indicator_enabled: bool) {
+    // The area the OAM takes up on screen, doubled for double size affine OAMs
+    pub fn get_bounding_size(&self) -> (usize, usize) {
+        let (width, height) = self.get_width_and_height();
+
+        if self.affine && self.double_size {
+            (width * 2, height * 2)
+        } else {
+            (width, height)
+        }
+    }
+
+    fn get_tint(&self, effects: &ObjEffects, selection_indicator_enabled: bool) -> Color32 {
+        if self.selected && selection_indicator_enabled {
+            return Color32::LIGHT_GREEN;
+        }
+
+        match self.mode {
+            OAMMode::Normal => Color32::WHITE,
+            // Premultiplied, so this ends up as sprite * EVA/16 + background * EVB/16 like the GBA does it
+            OAMMode::SemiTransparent => {
+                let eva = (effects.eva.min(16) as u32 * 255 / 16) as u8;
+                let alpha = ((16 - effects.evb.min(16)) as u32 * 255 / 16) as u8;
+                Color32::from_rgba_premultiplied(eva, eva, eva, alpha)
+            },
+            OAMMode::Window => Color32::from_rgba_premultiplied(0, 80, 100, 100)
+        }
+    }
+
+    // Every block of pixels takes the color of its top left pixel
+    fn draw_mosaic(&self, texture_sheet: &[TextureHandle], effects: &ObjEffects, ui: &mut Ui, tint: Color32) {
+        let pixel_size = 20.0 / 8.0;
+        let (width, height) = self.get_width_and_height();
+        let (pixel_width, pixel_height) = (width * 8, height * 8);
+        let origin = pos2((self.x as f32) * pixel_size, (self.y as f32) * pixel_size);
+
+        for block_y in (0..pixel_height).step_by(effects.mosaic_height.max(1)) {
+            for block_x in (0..pixel_width).step_by(effects.mosaic_width.max(1)) {
+                let (tile, pixel) = match self.get_pixel_location(block_x, block_y) {
+                    Some(location) => location,
+                    None => continue
+                };
+
+                let source = match texture_sheet.get(tile) {
+                    Some(source) => source,
+                    None => continue
+                };
+
+                // Stretching a single texel over the whole block
+                let texel = pos2(((pixel % 8) as f32 + 0.5) / 8.0, ((pixel / 8) as f32 + 0.5) / 8.0);
+                let block_size = vec2(
+                    effects.mosaic_width.min(pixel_width - block_x) as f32,
+                    effects.mosaic_height.min(pixel_height - block_y) as f32
+                );
+                let block_rect = Rect::from_min_size(origin + vec2(block_x as f32, block_y as f32) * pixel_size, block_size * pixel_size);
+
+                let mut mesh = Mesh::with_texture(source.id());
+                mesh.add_rect_with_uv(block_rect, Rect::from_min_max(texel, texel), tint);
+                ui.painter().add(mesh);
+            }
+        }
+    }
+
+    fn draw_affine(&self, textures: &[Vec<TextureHandle>], affine_matrices: &[AffineMatrix], effects: &ObjEffects, ui: &mut Ui, selection_indicator_enabled: bool) {
+        let sprite_size = 20.0;
+        let pixel_size = sprite_size / 8.0;
+        let (width, height) = self.get_width_and_height();
+        let (bounding_width, bounding_height) = self.get_bounding_size();
+
+        let texture_sheet = match textures.get(self.palette) {
+            Some(texture) => texture,
+            None => return
+        };
+
+        // Matrices that haven't been made yet act like they don't rotate or scale
+        let [a, b, c, d] = affine_matrices
+            .get(self.affine_matrix)
+            .map(|matrix| matrix.get_transform())
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+
+        let bounding_rect = Rect::from_min_size(
+            pos2((self.x as f32) * pixel_size, (self.y as f32) * pixel_size),
+            vec2(sprite_size * bounding_width as f32, sprite_size * bounding_height as f32)
+        );
+        let center = bounding_rect.center();
+
+        // Anything that ends up outside of the bounding box gets cut off, just like on hardware
+        let painter = ui.painter().with_clip_rect(bounding_rect.intersect(ui.clip_rect()));
+        let tint = self.get_tint(effects, selection_indicator_enabled);
+
+        for y in 0..height {
+            for x in 0..width {
+                let source = match texture_sheet.get(self.tile + x + y * 32) {
+                    Some(source) => source,
+                    None => continue
+                };
+
+                // Corners of the tile relative to the middle of the sprite, in pixels
+                let left = (x * 8) as f32 - (width * 4) as f32;
+                let top = (y * 8) as f32 - (height * 4) as f32;
+                let transform = |local_x: f32, local_y: f32| {
+                    center + vec2(a * local_x + b * local_y, c * local_x + d * local_y) * pixel_size
+                };
+
+                let mut mesh = Mesh::with_texture(source.id());
+                mesh.vertices.push(egui::epaint::Vertex { pos: transform(left, top), uv: pos2(0.0, 0.0), color: tint });
+                mesh.vertices.push(egui::epaint::Vertex { pos: transform(left + 8.0, top), uv: pos2(1.0, 0.0), color: tint });
+                mesh.vertices.push(egui::epaint::Vertex { pos: transform(left + 8.0, top + 8.0), uv: pos2(1.0, 1.0), color: tint });
+                mesh.vertices.push(egui::epaint::Vertex { pos: transform(left, top + 8.0), uv: pos2(0.0, 1.0), color: tint });
+                mesh.add_triangle(0, 1, 2);
+                mesh.add_triangle(0, 2, 3);
+
+                painter.add(mesh);
+            }
+        }
+
+        if self.selected && selection_indicator_enabled {
+            ui.painter().rect_stroke(bounding_rect, 0, Stroke::new(2.0, Color32::RED), egui::StrokeKind::Outside);
+        }
+    }
+
+    fn draw_tiles(&self, textures: &[Vec<TextureHandle>], ui: &mut Ui, tint: Color32) {
         let oam_sprites = self.get_sprite_indexes();
             
         let sprite_size = 20.0;
@@ -246,10 +504,7 @@ impl OAM {
                         _ => {}
                     }
 
-                    if self.selected && selection_indicator_enabled {
-                        
-                        texture = texture.tint(Color32::LIGHT_GREEN);
-                    }
+                    texture = texture.tint(tint);
                     
                     ui.add(
                         texture.fit_to_exact_size(vec2(sprite_size, sprite_size))
@@ -259,6 +514,31 @@ impl OAM {
                 //ui.allocate_space(vec2(sprite_size, sprite_size));
             }
         }
+    }
+
+    pub fn draw(&self, textures: &Vec<Vec<TextureHandle>>, affine_matrices: &[AffineMatrix], effects: &ObjEffects, ui: &mut Ui, selection_indicator_enabled: bool) {
+        if self.mode == OAMMode::Window && !effects.obj_windows_visible {return;}
+
+        // Mosaic isn't previewed on affine OAMs
+        if self.affine {
+            self.draw_affine(textures, affine_matrices, effects, ui, selection_indicator_enabled);
+            return;
+        }
+
+        // The GBA doesn't draw normal OAMs with the double size bit set at all
+        if self.double_size {return;}
+
+        let sprite_size = 20.0;
+        let (width, height) = self.get_width_and_height();
+        let tint = self.get_tint(effects, selection_indicator_enabled);
+
+        if self.mosaic && (effects.mosaic_width > 1 || effects.mosaic_height > 1) {
+            if let Some(texture_sheet) = textures.get(self.palette) {
+                self.draw_mosaic(texture_sheet, effects, ui, tint);
+            }
+        } else {
+            self.draw_tiles(textures, ui, tint);
+        }
 
         if self.selected && selection_indicator_enabled {
             ui.painter().rect_stroke(
@@ -272,6 +552,14 @@ impl OAM {
     }
 }
 
+// Where the cel's (0, 0) ends up when it's re-anchored
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CelAnchor {
+    Center,
+    // Horizontally centered, at the bottom edge
+    Bottom
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct AnimationCel {
     pub name: String,
@@ -285,11 +573,35 @@ fn parse_hex_string(string: &str) -> Option<u8> {
     }
 }
 
+// Suggests a name like name_copy, name_copy2, name_copy3...
+pub fn get_unique_name(name: &str, is_name_taken: impl Fn(&str) -> bool) -> String {
+    let mut unique_name = format!("{}_copy", name);
+    let mut i = 2;
+
+    while is_name_taken(&unique_name) {
+        unique_name = format!("{}_copy{}", name, i);
+        i += 1;
+    }
+
+    unique_name
+}
+
 impl AnimationCel {
     pub fn from_c(c: &str, name: &str) -> Option<AnimationCel> {
         let oam_regex = Regex::new(r"0x[0-9a-fA-F]{4}").unwrap();
+        let comment_regex = Regex::new(r"(?s)/\*(.*?)\*/").unwrap();
+
+        // Comments are blanked out so hex in them isn't read as OAM data
+        let mut code = c.to_string();
+        let mut comments = Vec::new();
 
-        let words: Vec<_> = oam_regex.find_iter(c).map(|m| m.as_str()).collect();
+        for comment in comment_regex.captures_iter(c) {
+            let whole = comment.get(0)?;
+            code.replace_range(whole.range(), &" ".repeat(whole.len()));
+            comments.push((whole.start(), comment[1].trim().to_string()));
+        }
+
+        let words: Vec<_> = oam_regex.find_iter(&code).collect();
         let mut i = 0;
 
         let mut oams: Vec<OAM> = Vec::new();
@@ -297,16 +609,16 @@ impl AnimationCel {
         while i < words.len() {
             let mut bytes: Vec<u8> = Vec::new();
 
-            let word1 = words.get(i)?;
-            let word2 = words.get(i + 1)?;
+            let word1 = words.get(i)?.as_str();
+            let word2 = words.get(i + 1)?.as_str();
             let word3 = words.get(i + 2)?;
 
             let byte1 = parse_hex_string(&word1[2..4])?;
             let byte2 = parse_hex_string(&word1[4..6])?;
             let byte3 = parse_hex_string(&word2[2..4])?;
             let byte4 = parse_hex_string(&word2[4..6])?;
-            let byte5 = parse_hex_string(&word3[2..4])?;
-            let byte6 = parse_hex_string(&word3[4..6])?;
+            let byte5 = parse_hex_string(&word3.as_str()[2..4])?;
+            let byte6 = parse_hex_string(&word3.as_str()[4..6])?;
             
             bytes.push(byte1);
             bytes.push(byte2);
@@ -317,7 +629,14 @@ impl AnimationCel {
 
             i += 3;
 
-            let oam = OAM::new(&bytes);
+            let mut oam = OAM::new(&bytes);
+
+            // A comment after the last word on the same line is the OAM's label
+            let line_end = c[word3.end()..].find('\n').map(|end| word3.end() + end).unwrap_or(c.len());
+            if let Some((_, label)) = comments.iter().find(|(start, _)| (word3.end()..line_end).contains(start)) {
+                oam.label = label.clone();
+            }
+
             oams.push(oam);
         }
 
@@ -346,9 +665,87 @@ impl AnimationCel {
         Some(AnimationCel { name, oams })
     }
 
-    pub fn draw(&self, textures: &Vec<Vec<TextureHandle>>, ui: &mut Ui, selection_indicator_enabled: bool) {
-        for oam in self.oams.iter().rev() {
-            oam.draw(textures, ui, selection_indicator_enabled);
+    // The smallest rectangle around every OAM in pixels, as (left, top, right, bottom)
+    pub fn get_bounds(&self) -> Option<(i32, i32, i32, i32)> {
+        self.oams.iter().map(|oam| {
+            let (width, height) = oam.get_bounding_size();
+            (oam.x as i32, oam.y as i32, oam.x as i32 + width as i32 * 8, oam.y as i32 + height as i32 * 8)
+        }).reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+    }
+
+    // Only changes anything if every OAM still fits in the -128 to 127 range
+    fn set_positions(&mut self, positions: &[(i32, i32)]) -> Result<(), String> {
+        let in_range = |position: i32| position >= i8::MIN as i32 && position <= i8::MAX as i32;
+
+        if !positions.iter().all(|&(x, y)| in_range(x) && in_range(y)) {
+            return Err(String::from("That would move OAMs outside of -128 to 127"));
+        }
+
+        for (oam, &(x, y)) in self.oams.iter_mut().zip(positions) {
+            oam.x = x as i8;
+            oam.y = y as i8;
+        }
+
+        Ok(())
+    }
+
+    pub fn translate(&mut self, x: i32, y: i32) -> Result<(), String> {
+        let positions: Vec<(i32, i32)> = self.oams.iter().map(|oam| (oam.x as i32 + x, oam.y as i32 + y)).collect();
+        self.set_positions(&positions)
+    }
+
+    // Mirrors the cel around the vertical line at `axis.0` and/or the horizontal line at `axis.1`.
+    // Affine OAMs are only moved, their flipping is up to their matrix.
+    pub fn mirror(&mut self, horizontal: bool, vertical: bool, axis: (i32, i32)) -> Result<(), String> {
+        let positions: Vec<(i32, i32)> = self.oams.iter().map(|oam| {
+            let (width, height) = oam.get_bounding_size();
+            let x = if horizontal { axis.0 * 2 - oam.x as i32 - width as i32 * 8 } else { oam.x as i32 };
+            let y = if vertical { axis.1 * 2 - oam.y as i32 - height as i32 * 8 } else { oam.y as i32 };
+            (x, y)
+        }).collect();
+
+        self.set_positions(&positions)?;
+
+        for oam in self.oams.iter_mut().filter(|oam| !oam.affine) {
+            let flipped_horizontally = matches!(oam.flip, OAMFlip::Horizontal | OAMFlip::Both) != horizontal;
+            let flipped_vertically = matches!(oam.flip, OAMFlip::Vertical | OAMFlip::Both) != vertical;
+
+            oam.flip = match (flipped_horizontally, flipped_vertically) {
+                (false, false) => OAMFlip::None,
+                (true, false) => OAMFlip::Horizontal,
+                (false, true) => OAMFlip::Vertical,
+                (true, true) => OAMFlip::Both
+            };
+        }
+
+        Ok(())
+    }
+
+    // Moves the OAMs so (0, 0) is at the anchor of their bounding box
+    pub fn anchor(&mut self, anchor: CelAnchor) -> Result<(), String> {
+        let (left, top, right, bottom) = match self.get_bounds() {
+            Some(bounds) => bounds,
+            None => return Ok(())
+        };
+
+        let center_x = (left + right).div_euclid(2);
+
+        match anchor {
+            CelAnchor::Center => self.translate(-center_x, -(top + bottom).div_euclid(2)),
+            CelAnchor::Bottom => self.translate(-center_x, -bottom)
+        }
+    }
+
+    pub fn draw(&self, textures: &Vec<Vec<TextureHandle>>, affine_matrices: &[AffineMatrix], effects: &ObjEffects, ui: &mut Ui, selection_indicator_enabled: bool) {
+        // Lower priorities go on top, then OAMs earlier in the list go on top of later ones
+        let draw_order = self.oams
+            .iter()
+            .enumerate()
+            .filter(|(_, oam)| !oam.hidden)
+            .sorted_by_key(|&(i, oam)| (Reverse(oam.priority), Reverse(i)));
+
+        for (_, oam) in draw_order {
+            oam.draw(textures, affine_matrices, effects, ui, selection_indicator_enabled);
         }
     }
 }
@@ -368,6 +765,14 @@ pub struct PositionedAnimationFrame {
     id: usize
 }
 
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CopiedAnimationFrame {
+    pub cell: String,
+    // Position relative to the first copied frame
+    pub offset: isize,
+    pub duration: u8
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Animation {
     pub frames: Vec<AnimationFrame>,
@@ -574,7 +979,9 @@ impl Animation {
     pub fn insert_anim_frame(&mut self, cell: String, position: isize) {
         let mut positioned_frames = Animation::convert_duration_frames_to_positioned(&self.frames);
         
-        positioned_frames.push(PositionedAnimationFrame { cell, position, id: positioned_frames.len() + 1 });
+        // Pasted frames can leave gaps in the ids, so counting the frames isn't enough
+        let id = self.frames.iter().map(|f| f.id).max().map_or(0, |id| id + 1);
+        positioned_frames.push(PositionedAnimationFrame { cell, position, id });
         
         self.frames = Animation::convert_positioned_frames_to_duration(positioned_frames, self.duration);
     }
@@ -590,6 +997,79 @@ impl Animation {
         }
     }
 
+    pub fn copy_anim_frames(&self, frame_ids: &[usize]) -> Vec<CopiedAnimationFrame> {
+        let mut copied_frames = Vec::new();
+        let mut first_position = None;
+        let mut position = 0;
+
+        for frame in &self.frames {
+            if frame_ids.contains(&frame.id) {
+                let first_position = *first_position.get_or_insert(position);
+
+                copied_frames.push(CopiedAnimationFrame {
+                    cell: frame.cell.clone(),
+                    offset: position - first_position,
+                    duration: frame.duration
+                });
+            }
+
+            position += frame.duration as isize;
+        }
+
+        copied_frames
+    }
+
+    // Overwrites everything between the position and the end of the last pasted frame,
+    // returns the ids given to the pasted frames
+    pub fn paste_anim_frames(&mut self, copied_frames: &[CopiedAnimationFrame], position: isize) -> Vec<usize> {
+        let mut pasted_ids = Vec::new();
+
+        let last_frame = match copied_frames.last() {
+            Some(frame) => frame,
+            None => return pasted_ids
+        };
+
+        // Nothing would be shown before the first frame otherwise
+        let position = if self.frames.is_empty() { 0 } else { position };
+        let end = position + last_frame.offset + last_frame.duration as isize;
+
+        let mut positioned_frames = Animation::convert_duration_frames_to_positioned(&self.frames);
+        let mut next_id = self.frames.iter().map(|f| f.id).max().map_or(0, |id| id + 1);
+
+        // Whatever was showing where the pasted frames end should keep showing after them
+        let frame_at_end = positioned_frames.iter().rev().find(|f| f.position < end).map(|f| f.cell.clone());
+        let keyframe_at_end = positioned_frames.iter().any(|f| f.position == end);
+
+        positioned_frames.retain(|f| f.position < position || f.position >= end);
+
+        if let Some(cell) = frame_at_end {
+            if !keyframe_at_end && end < self.duration as isize {
+                positioned_frames.push(PositionedAnimationFrame { cell, position: end, id: next_id });
+                next_id += 1;
+            }
+        }
+
+        for frame in copied_frames {
+            let frame_position = position + frame.offset;
+
+            // The first frame is always expected to have an id of 0
+            let id = if frame_position == 0 && !positioned_frames.iter().any(|f| f.id == 0) {
+                0
+            } else {
+                next_id += 1;
+                next_id - 1
+            };
+
+            positioned_frames.push(PositionedAnimationFrame { cell: frame.cell.clone(), position: frame_position, id });
+            pasted_ids.push(id);
+        }
+
+        self.duration = self.duration.max(end as usize);
+        self.frames = Animation::convert_positioned_frames_to_duration(positioned_frames, self.duration);
+
+        pasted_ids
+    }
+
     pub fn get_minimum_duration(&self) -> usize {
         let positioned_frames = Animation::convert_duration_frames_to_positioned(&self.frames);
             
@@ -610,6 +1090,23 @@ impl Animation {
             frame.duration = (self.duration - minimum_duration) as u8;
         }
     }
+
+    // Returns the index and starting frame of every frame that uses the cel
+    pub fn get_cel_usages(&self, cel: &str) -> Vec<(usize, usize)> {
+        let mut usages = Vec::new();
+        let mut position = 0;
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            if frame.cell == cel {
+                usages.push((i, position));
+            }
+
+            position += frame.duration as usize;
+        }
+
+        usages
+    }
+
     pub fn get_used_cels(&self) -> Vec<&String> {
         let mut used_cels = Vec::new();
 
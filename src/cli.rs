@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use crate::{anim_parser::{AnimationCel, CelAnchor}, export, import};
+
+const USAGE: &str = "Usage:
+  yanimator --mirror h|v <cel> <project>
+  yanimator --translate <x>,<y> <cel> <project>
+  yanimator --anchor center|bottom <cel> <project>";
+
+type Transform = Box<dyn FnOnce(&mut AnimationCel) -> Result<(), String>>;
+
+fn parse_transform(operation: &str, value: &str) -> Result<Transform, String> {
+    match (operation, value) {
+        ("--mirror", "h") => Ok(Box::new(|cel| cel.mirror(true, false, (0, 0)))),
+        ("--mirror", "v") => Ok(Box::new(|cel| cel.mirror(false, true, (0, 0)))),
+        ("--mirror", _) => Err(format!("Unknown mirror direction \"{}\", expected h or v", value)),
+        ("--translate", _) => {
+            let (x, y) = value
+                .split_once(',')
+                .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+                .ok_or_else(|| format!("Couldn't read offset \"{}\", expected <x>,<y>", value))?;
+
+            Ok(Box::new(move |cel| cel.translate(x, y)))
+        },
+        ("--anchor", "center") => Ok(Box::new(|cel| cel.anchor(CelAnchor::Center))),
+        ("--anchor", "bottom") => Ok(Box::new(|cel| cel.anchor(CelAnchor::Bottom))),
+        ("--anchor", _) => Err(format!("Unknown anchor \"{}\", expected center or bottom", value)),
+        _ => Err(format!("Unknown option \"{}\"\n{}", operation, USAGE))
+    }
+}
+
+// Runs a cel transform on a project without opening the editor, saving it in place
+pub fn run(args: &[String]) -> Result<(), String> {
+    let [operation, value, cel_name, project_path] = args else {
+        return Err(String::from(USAGE));
+    };
+
+    let transform = parse_transform(operation, value)?;
+
+    if !Path::new(project_path).is_file() {
+        return Err(format!("Couldn't find project \"{}\"", project_path));
+    }
+
+    let mut project = import::load_project_json(project_path)
+        .map_err(|err| format!("Couldn't load project: {}", err))?;
+
+    let cel = project.animation_cels
+        .get_mut(cel_name)
+        .ok_or_else(|| format!("The project has no cel named \"{}\"", cel_name))?;
+
+    transform(cel)?;
+
+    export::create_project_json(project_path, &project)
+        .map_err(|err| format!("Couldn't save project: {}", err))
+}
@@ -1,8 +1,9 @@
-use std::{collections::HashMap, fs, io::Write};
+use std::{collections::HashMap, fs, io::{self, Write}};
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::{anim_parser::{Animation, AnimationCel, OAMFlip, OAMShape, OAMSize}, ProjectStructure};
+use crate::{anim_parser::{Animation, AnimationCel, OAMFlip, OAMMode, OAMShape, OAMSize, OAM}, ProjectStructure};
 
 /*
     
@@ -112,83 +113,120 @@ pub fn create_project_bin(path: &str, animation_cells: &HashMap<String, Animatio
     }    
 }
 
-pub fn create_project_json(path: &str, animation_cells: &HashMap<String, AnimationCel>, animations: &Vec<Animation>) {
-    let project = ProjectStructure {
-        animation_cels: animation_cells.clone(),
-        animations: animations.clone()
-    };
+pub fn create_project_json(path: &str, project: &ProjectStructure) -> io::Result<()> {
+    let bytes = serde_json::to_vec_pretty(project)?;
+    let mut file = fs::File::create(path)?;
 
-    let export = fs::File::create(path);
-    
-    if let Ok(mut file) = export {
-        if let Ok(bytes) = serde_json::to_vec_pretty(&project) {
-            let _ = file.write_all(&bytes);
-        }
-    }   
+    file.write_all(&bytes)
 }
 
-pub fn export_animation_cels(path: &str, animation_cells: &HashMap<String, AnimationCel>) {
-    let mut export = String::from("// Exported by Yanimator");
+// The three attribute words the GBA reads for an OAM
+pub fn get_oam_words(oam: &OAM) -> [u16; 3] {
+    let shape: u16 = match oam.shape {
+        OAMShape::Square => 0x0000,
+        OAMShape::Horizontal => 0x4000,
+        OAMShape::Vertical => 0x8000
+    };
 
-    for (name, cell) in animation_cells {
-        export.push_str(&format!("\n\nAnimationCel {}[] = {{\n", name));
-        export.push_str(&format!("    /* Len */ {},\n", cell.oams.len()));
+    let mut y = oam.y as i16;
+    if y < 0 {
+        y += 0x100;
+    }
 
-        let mut i = 0;
+    let mut word1: u16 = shape | y as u16;
 
-        for oam in &cell.oams {
-            export.push_str(&format!("    /* {:0fill$} */ ", i, fill = 3));
-            let shape: u16 = match oam.shape {
-                OAMShape::Square => 0x0000,
-                OAMShape::Horizontal => 0x4000,
-                OAMShape::Vertical => 0x8000
-            };
-
-            let mut y = oam.y as i16;
-            if y < 0 {
-                y += 0x100;
-            }
+    if oam.affine {
+        word1 |= 0x0100;
+    }
 
-            let word1: u16 = shape | y as u16;
+    if oam.double_size {
+        word1 |= 0x0200;
+    }
 
-            export.push_str(&format!("0x{:0fill$x}, ", word1, fill = 4));
+    word1 |= match oam.mode {
+        OAMMode::Normal => 0x0000,
+        OAMMode::SemiTransparent => 0x0400,
+        OAMMode::Window => 0x0800
+    };
 
-            let mut flip_size_nibble: u16 = match oam.size {
-                OAMSize::Size0 => 0x0000,
-                OAMSize::Size1 => 0x4000,
-                OAMSize::Size2 => 0x8000,
-                OAMSize::Size3 => 0xC000,
-            };
+    if oam.mosaic {
+        word1 |= 0x1000;
+    }
 
-            match oam.flip {
-                OAMFlip::None => {},
-                OAMFlip::Horizontal => {flip_size_nibble += 0x1000},
-                OAMFlip::Vertical => {flip_size_nibble += 0x2000},
-                OAMFlip::Both => {flip_size_nibble += 0x3000}
-            };
+    let mut flip_size_nibble: u16 = match oam.size {
+        OAMSize::Size0 => 0x0000,
+        OAMSize::Size1 => 0x4000,
+        OAMSize::Size2 => 0x8000,
+        OAMSize::Size3 => 0xC000,
+    };
 
-            let mut x = oam.x as i16;
-            if x < 0 {
-                x += 0x200;
-            }
+    // Affine OAMs store their matrix index where the flip bits would be
+    if oam.affine {
+        flip_size_nibble |= ((oam.affine_matrix & 0x1F) as u16) << 9;
+    } else {
+        match oam.flip {
+            OAMFlip::None => {},
+            OAMFlip::Horizontal => {flip_size_nibble += 0x1000},
+            OAMFlip::Vertical => {flip_size_nibble += 0x2000},
+            OAMFlip::Both => {flip_size_nibble += 0x3000}
+        };
+    }
+
+    let mut x = oam.x as i16;
+    if x < 0 {
+        x += 0x200;
+    }
+
+    let word2: u16 = flip_size_nibble | x as u16;
+
+    let palette = (oam.palette as u16) << 12;
+
+    let priority = ((oam.priority & 0x3) as u16) << 10;
 
-            let word2: u16 = flip_size_nibble | x as u16;
+    let tile = (oam.tile & 0x03FF) as u16;
 
-            export.push_str(&format!("0x{:0fill$x}, ", word2, fill = 4));
+    let word3: u16 = palette | priority | tile;
 
-            let palette = (oam.palette as u16) << 12;
+    [word1, word2, word3]
+}
+
+// Anything that would end the comment or the cel early is broken up. "*/" becomes "* /",
+// since just removing it would turn labels like "**//" into a new "*/".
+pub fn get_label_comment(oam: &OAM) -> String {
+    let label = oam.label.replace("*/", "* /").replace([';', '\r', '\n'], " ");
+
+    if label.trim().is_empty() {
+        String::new()
+    } else {
+        format!(" /* {} */", label.trim())
+    }
+}
 
-            let tile = (oam.tile & 0x0FFF) as u16;
+pub fn export_animation_cels(path: &str, animation_cells: &HashMap<String, AnimationCel>) {
+    let mut export = String::from("// Exported by Yanimator");
 
-            let word3: u16 = palette | tile;
+    // Sorted so the output doesn't change order between exports
+    for (name, cell) in animation_cells.iter().sorted_by_key(|(name, _)| *name) {
+        export.push_str(&format!("\n\nAnimationCel {}[] = {{\n", name));
+        export.push_str(&format!("    /* Len */ {},\n", cell.oams.len()));
 
+        let mut i = 0;
 
-            export.push_str(&format!("0x{:0fill$x}", word3, fill = 4));
+        for oam in &cell.oams {
+            export.push_str(&format!("    /* {:0fill$} */ ", i, fill = 3));
+            let [word1, word2, word3] = get_oam_words(oam);
+            export.push_str(&format!("0x{:0fill$x}, 0x{:0fill$x}, 0x{:0fill$x}", word1, word2, word3, fill = 4));
 
             i += 1;
 
             if i < cell.oams.len() {
-                export.push_str(",\n");
+                export.push(',');
+            }
+
+            export.push_str(&get_label_comment(oam));
+
+            if i < cell.oams.len() {
+                export.push('\n');
             }
         }
 
@@ -1,4 +1,6 @@
-use std::{collections::HashMap, fs};
+use std::{cmp::Ordering, collections::HashMap, fs, path::Path, sync::LazyLock};
+
+use regex::Regex;
 
 use crate::{anim_parser::{Animation, AnimationCel}, ProjectStructure};
 
@@ -86,7 +88,7 @@ pub fn load_project(path_str: &str) -> (HashMap<String, AnimationCel>, Vec<Anima
     (animation_cels, animations)
 }
 
-pub fn load_project_json(path_str: &str) -> Result<(HashMap<String, AnimationCel>, Vec<Animation>), serde_json::Error> {
+pub fn load_project_json(path_str: &str) -> Result<ProjectStructure, serde_json::Error> {
     let project_str = fs::read(path_str).unwrap();
     let mut project: ProjectStructure = serde_json::from_slice(&project_str)?;
 
@@ -94,7 +96,7 @@ pub fn load_project_json(path_str: &str) -> Result<(HashMap<String, AnimationCel
         animation.duration = animation.get_total_frames();
     }
 
-    Ok((project.animation_cels, project.animations))
+    Ok(project)
 }
 
 pub fn load_animation_cels_from_c(path_str: &str) -> HashMap<String, AnimationCel> {
@@ -151,4 +153,99 @@ pub fn load_animations_from_c(path_str: &str) -> Vec<Animation> {
             Animation::from_c(&anim_str, &anim_name)
         })
         .collect()
-}
\ No newline at end of file
+}
+
+pub struct SequenceFrame {
+    pub path: String,
+    pub file_name: String,
+    // None if the frame should use the default duration
+    pub duration: Option<u8>
+}
+
+static CHUNK_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+|\D+").unwrap());
+
+// Compares numbers inside of names by value, so frame_2 comes before frame_10
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let a_chunks: Vec<&str> = CHUNK_REGEX.find_iter(a).map(|m| m.as_str()).collect();
+    let b_chunks: Vec<&str> = CHUNK_REGEX.find_iter(b).map(|m| m.as_str()).collect();
+
+    for (a_chunk, b_chunk) in a_chunks.iter().zip(b_chunks.iter()) {
+        let ordering = match (a_chunk.parse::<u64>(), b_chunk.parse::<u64>()) {
+            (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+            _ => a_chunk.cmp(b_chunk)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    a_chunks.len().cmp(&b_chunks.len())
+}
+
+// Keyframes can't be shorter than a frame or longer than a u8
+fn clamp_duration(duration: u64) -> u8 {
+    duration.clamp(1, u8::MAX as u64) as u8
+}
+
+/*
+Frame durations are read from (in order of priority):
+
+durations.json in the folder, either a list of durations in frame order
+or an object of file names to durations
+A "_d<duration>" suffix in the file name, like "walk_03_d6.png"
+*/
+pub fn load_png_sequence(folder: &str) -> Result<Vec<SequenceFrame>, std::io::Error> {
+    let duration_regex = Regex::new(r"_d(\d+)$").unwrap();
+    let mut frames = Vec::new();
+
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+
+        let is_png = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        if !is_png {continue;}
+
+        let (path_str, file_name, stem) = match (path.to_str(), path.file_name().and_then(|name| name.to_str()), path.file_stem().and_then(|stem| stem.to_str())) {
+            (Some(path_str), Some(file_name), Some(stem)) => (path_str, file_name, stem),
+            _ => continue
+        };
+
+        let duration = duration_regex
+            .captures(stem)
+            .and_then(|captures| captures[1].parse().ok())
+            .map(clamp_duration);
+
+        frames.push(SequenceFrame {
+            path: String::from(path_str),
+            file_name: String::from(file_name),
+            duration
+        });
+    }
+
+    frames.sort_by(|a, b| compare_natural(&a.file_name, &b.file_name));
+
+    if let Ok(json) = fs::read_to_string(Path::new(folder).join("durations.json")) {
+        match serde_json::from_str::<serde_json::Value>(&json) {
+            Ok(serde_json::Value::Array(durations)) => {
+                for (frame, duration) in frames.iter_mut().zip(durations) {
+                    if let Some(duration) = duration.as_u64() {
+                        frame.duration = Some(clamp_duration(duration));
+                    }
+                }
+            },
+            Ok(serde_json::Value::Object(durations)) => {
+                for frame in frames.iter_mut() {
+                    let stem = Path::new(&frame.file_name).file_stem().and_then(|stem| stem.to_str()).unwrap_or(&frame.file_name);
+                    let duration = durations.get(&frame.file_name).or(durations.get(stem));
+
+                    if let Some(duration) = duration.and_then(|duration| duration.as_u64()) {
+                        frame.duration = Some(clamp_duration(duration));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(frames)
+}
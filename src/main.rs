@@ -5,12 +5,12 @@ use eframe::egui;
 use egui::{Rect, TextureHandle, Visuals};
 use egui_extras::install_image_loaders;
 use palette_parser::Palette;
-use panels::{animation_cells::AnimationCellsPanel, timeline::Timeline};
+use panels::{affine_matrices::AffineMatricesPanel, animation_cells::AnimationCellsPanel, cel_transform::CelTransformPanel, cel_usage::CelUsagePanel, oam_generator::OamGeneratorPanel, palette_editor::PaletteEditorPanel, remap::RemapPanel, sequence_importer::SequenceImporterPanel, spritesheet::SpritesheetPanel, spritesheet_optimizer::SpritesheetOptimizerPanel, tile_editor::TileEditorPanel, timeline::Timeline};
 use serde::{Deserialize, Serialize};
 use sprite_parser::Spritesheet;
-use anim_parser::{Animation, AnimationCel};
+use anim_parser::{AffineMatrix, Animation, AnimationCel, OAM};
 
-use crate::panels::{topbar::Topbar, viewport::Viewport};
+use crate::panels::{topbar::Topbar, viewport::{Backdrop, ReferenceImage, Viewport}};
 
 mod palette_parser;
 mod sprite_parser;
@@ -18,8 +18,23 @@ mod anim_parser;
 mod export;
 mod panels;
 mod import;
+mod tilemap_parser;
+mod oam_fitter;
+mod sheet_optimizer;
+mod cli;
 
 fn main() -> eframe::Result {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if !args.is_empty() {
+        if let Err(message) = cli::run(&args) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions::default();
     eframe::run_native("Yanimator", native_options, Box::new(|cc| Ok(Box::new(Yanimator::new(cc)))))
 }
@@ -44,6 +59,7 @@ struct Yanimator {
     
 
     animations: Vec<Animation>,
+    affine_matrices: Vec<AffineMatrix>,
     last_frame_time: Instant,
     frames: usize,
    
@@ -52,13 +68,35 @@ struct Yanimator {
     timeline: Timeline,
     animation_cells_panel: AnimationCellsPanel,
     topbar: Topbar,
-    viewport: Viewport
+    viewport: Viewport,
+    cel_usage_panel: CelUsagePanel,
+    palette_editor_panel: PaletteEditorPanel,
+    tile_editor_panel: TileEditorPanel,
+    oam_generator_panel: OamGeneratorPanel,
+    sequence_importer_panel: SequenceImporterPanel,
+    spritesheet_optimizer_panel: SpritesheetOptimizerPanel,
+    affine_matrices_panel: AffineMatricesPanel,
+    cel_transform_panel: CelTransformPanel,
+    spritesheet_panel: SpritesheetPanel,
+    remap_panel: RemapPanel,
+
+    // Set when text is being typed or a modal is open, so shortcuts don't fire
+    keyboard_captured: bool,
+    // Text that will be put in the system clipboard at the end of the frame
+    clipboard_text: Option<String>,
+    oam_clipboard: Vec<OAM>
 }
 
 #[derive(Deserialize, Serialize)]
 struct ProjectStructure {
     animation_cels: HashMap<String, AnimationCel>,
-    animations: Vec<Animation>
+    animations: Vec<Animation>,
+    #[serde(default)]
+    reference_image: Option<ReferenceImage>,
+    #[serde(default)]
+    backdrop: Option<Backdrop>,
+    #[serde(default)]
+    affine_matrices: Vec<AffineMatrix>
 }
 
 impl Yanimator {
@@ -77,16 +115,45 @@ impl Yanimator {
             palette, 
             animation_cels,
             animations,
+            affine_matrices: Vec::new(),
             last_frame_time: Instant::now(),
             frames: 0,
             timeline: Timeline::init(),
             animation_cells_panel: AnimationCellsPanel::init(),
             topbar: Topbar::init(),
             viewport: Viewport::init(),
+            cel_usage_panel: CelUsagePanel::init(),
+            palette_editor_panel: PaletteEditorPanel::init(),
+            tile_editor_panel: TileEditorPanel::init(),
+            oam_generator_panel: OamGeneratorPanel::init(),
+            sequence_importer_panel: SequenceImporterPanel::init(),
+            spritesheet_optimizer_panel: SpritesheetOptimizerPanel::init(),
+            affine_matrices_panel: AffineMatricesPanel::init(),
+            cel_transform_panel: CelTransformPanel::init(),
+            spritesheet_panel: SpritesheetPanel::init(),
+            remap_panel: RemapPanel::init(),
             editing_cell: String::from(""),
             editing_oam: 0,
-            spritesheet_palette: 0
+            spritesheet_palette: 0,
+            keyboard_captured: false,
+            clipboard_text: None,
+            oam_clipboard: Vec::new()
+        }
+    }
+
+    // Everything that switches the cel or OAM being edited goes through here, so OAMs
+    // selected before don't stay selected where they can't be seen
+    fn edit_oam(&mut self, cell: &str, oam: usize) {
+        for oam in self.animation_cels.values_mut().flat_map(|cel| cel.oams.iter_mut()) {
+            oam.selected = false;
         }
+
+        self.editing_cell = String::from(cell);
+        self.editing_oam = oam;
+    }
+
+    fn is_modal_open(&self) -> bool {
+        self.animation_cells_panel.is_modal_open() || self.topbar.is_modal_open() || self.cel_usage_panel.is_modal_open()
     }
 }
 
@@ -97,17 +164,17 @@ impl eframe::App for Yanimator {
         
         let now = Instant::now();
         let elapsed = now.duration_since(self.last_frame_time).as_secs_f32();
-        
-        if elapsed >= 1.0 / 60.0 {
-            if self.timeline.playing {self.frames += 1;}
-            self.last_frame_time = now;
-        }
+        self.last_frame_time = now;
+
+        self.frames += self.timeline.advance_playback(elapsed);
         
         let animation = self.animations.get_mut(self.animation_id);
         
         if let Some(animation) = animation {
-            if self.frames >= animation.get_total_frames() {
-                self.frames = 0;
+            let total_frames = animation.get_total_frames();
+
+            if self.frames >= total_frames {
+                self.frames = if total_frames > 0 { self.frames % total_frames } else { 0 };
             }
             
             animation.current_frame = animation.get_anim_frame_from_frames(self.frames);
@@ -115,6 +182,8 @@ impl eframe::App for Yanimator {
         
         ctx.request_repaint();
         
+        self.keyboard_captured = ctx.wants_keyboard_input() || self.is_modal_open();
+
         ctx.input(|i| {
             match self.state {
                 AppState::AnimationEditor => {
@@ -123,6 +192,7 @@ impl eframe::App for Yanimator {
                 },
                 AppState::CellEditor => {
                     panels::properties::input(i, self);
+                    panels::oams::input(i, self);
                 }
             }
             
@@ -167,5 +237,81 @@ impl eframe::App for Yanimator {
         egui::CentralPanel::default().show(ctx, |ui| {
             panels::viewport::ui(ui, self)
         });
+
+        let mut cel_usage_open = self.cel_usage_panel.open;
+        egui::Window::new("Cel Usage")
+            .open(&mut cel_usage_open)
+            .show(ctx, |ui| {
+                panels::cel_usage::ui(ui, self)
+            });
+        self.cel_usage_panel.open = cel_usage_open;
+
+        let mut palette_editor_open = self.palette_editor_panel.open;
+        egui::Window::new("Palette")
+            .open(&mut palette_editor_open)
+            .show(ctx, |ui| {
+                panels::palette_editor::ui(ui, self)
+            });
+        self.palette_editor_panel.open = palette_editor_open;
+
+        let mut tile_editor_open = self.tile_editor_panel.open;
+        egui::Window::new("Tile Editor")
+            .open(&mut tile_editor_open)
+            .show(ctx, |ui| {
+                panels::tile_editor::ui(ui, self)
+            });
+        self.tile_editor_panel.open = tile_editor_open;
+
+        let mut oam_generator_open = self.oam_generator_panel.open;
+        egui::Window::new("Generate Cel from Image")
+            .open(&mut oam_generator_open)
+            .show(ctx, |ui| {
+                panels::oam_generator::ui(ui, self)
+            });
+        self.oam_generator_panel.open = oam_generator_open;
+
+        let mut sequence_importer_open = self.sequence_importer_panel.open;
+        egui::Window::new("Import PNG Sequence")
+            .open(&mut sequence_importer_open)
+            .show(ctx, |ui| {
+                panels::sequence_importer::ui(ui, self)
+            });
+        self.sequence_importer_panel.open = sequence_importer_open;
+
+        let mut spritesheet_optimizer_open = self.spritesheet_optimizer_panel.open;
+        egui::Window::new("Optimize Spritesheet")
+            .open(&mut spritesheet_optimizer_open)
+            .show(ctx, |ui| {
+                panels::spritesheet_optimizer::ui(ui, self)
+            });
+        self.spritesheet_optimizer_panel.open = spritesheet_optimizer_open;
+
+        let mut affine_matrices_open = self.affine_matrices_panel.open;
+        egui::Window::new("Affine Matrices")
+            .open(&mut affine_matrices_open)
+            .show(ctx, |ui| {
+                panels::affine_matrices::ui(ui, self)
+            });
+        self.affine_matrices_panel.open = affine_matrices_open;
+
+        let mut cel_transform_open = self.cel_transform_panel.open;
+        egui::Window::new("Transform Cel")
+            .open(&mut cel_transform_open)
+            .show(ctx, |ui| {
+                panels::cel_transform::ui(ui, self)
+            });
+        self.cel_transform_panel.open = cel_transform_open;
+
+        let mut remap_open = self.remap_panel.open;
+        egui::Window::new("Remap Tiles and Palettes")
+            .open(&mut remap_open)
+            .show(ctx, |ui| {
+                panels::remap::ui(ui, self)
+            });
+        self.remap_panel.open = remap_open;
+
+        if let Some(text) = self.clipboard_text.take() {
+            ctx.copy_text(text);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,342 @@
+use crate::{anim_parser::{OAMFlip, OAMMode, OAMShape, OAMSize, OAM}, palette_parser::RGB, sprite_parser::{Sprite, Spritesheet, MAX_TILE, SHEET_WIDTH}};
+
+// Every OAM shape with its width and height in tiles, biggest first
+const OAM_SHAPES: [(OAMShape, OAMSize, usize, usize); 12] = [
+    (OAMShape::Square, OAMSize::Size3, 8, 8),
+    (OAMShape::Horizontal, OAMSize::Size3, 8, 4),
+    (OAMShape::Vertical, OAMSize::Size3, 4, 8),
+    (OAMShape::Square, OAMSize::Size2, 4, 4),
+    (OAMShape::Horizontal, OAMSize::Size2, 4, 2),
+    (OAMShape::Vertical, OAMSize::Size2, 2, 4),
+    (OAMShape::Square, OAMSize::Size1, 2, 2),
+    (OAMShape::Horizontal, OAMSize::Size1, 4, 1),
+    (OAMShape::Vertical, OAMSize::Size1, 1, 4),
+    (OAMShape::Horizontal, OAMSize::Size0, 2, 1),
+    (OAMShape::Vertical, OAMSize::Size0, 1, 2),
+    (OAMShape::Square, OAMSize::Size0, 1, 1),
+];
+
+// The OAM shape and size that's exactly `width` by `height` tiles, if there is one
+pub fn get_shape_for_size(width: usize, height: usize) -> Option<(OAMShape, OAMSize)> {
+    OAM_SHAPES
+        .iter()
+        .find(|&&(_, _, shape_width, shape_height)| shape_width == width && shape_height == height)
+        .map(|&(shape, size, _, _)| (shape, size))
+}
+
+// Covers a `width` by `height` tile area with as few OAMs as it can, as (x, y, shape, size) in tiles
+pub fn split_into_shapes(width: usize, height: usize) -> Vec<(usize, usize, OAMShape, OAMSize)> {
+    let mut covered = vec![false; width * height];
+    let mut shapes = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if covered[x + y * width] {continue;}
+
+            // There's always at least the 8x8 shape that fits
+            let &(shape, size, shape_width, shape_height) = match OAM_SHAPES.iter().find(|&&(_, _, shape_width, shape_height)| {
+                x + shape_width <= width && y + shape_height <= height
+                    && (0..shape_height).all(|dy| (0..shape_width).all(|dx| !covered[x + dx + (y + dy) * width]))
+            }) {
+                Some(found) => found,
+                None => continue
+            };
+
+            for dy in 0..shape_height {
+                for dx in 0..shape_width {
+                    covered[x + dx + (y + dy) * width] = true;
+                }
+            }
+
+            shapes.push((x, y, shape, size));
+        }
+    }
+
+    shapes
+}
+
+const FLIPS: [OAMFlip; 4] = [OAMFlip::None, OAMFlip::Horizontal, OAMFlip::Vertical, OAMFlip::Both];
+
+// An image where every pixel is a color index of a 16 color palette
+#[derive(Clone, PartialEq)]
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>
+}
+
+impl IndexedImage {
+    // Transparent pixels become color 0, everything else is matched to the closest palette color
+    pub fn from_file(file_path: &str, palette: &[RGB]) -> Result<IndexedImage, image::ImageError> {
+        let image = image::open(file_path)?.to_rgba8();
+
+        let pixels = image.pixels().map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            if a < 128 {return 0;}
+
+            palette
+                .iter()
+                .enumerate()
+                .skip(1)
+                .min_by_key(|(_, color)| {
+                    let distance = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+                    distance(color.r, r) + distance(color.g, g) + distance(color.b, b)
+                })
+                .map(|(i, _)| i as u8)
+                .unwrap_or(0)
+        }).collect();
+
+        Ok(IndexedImage {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        if x >= self.width || y >= self.height {return 0;}
+        self.pixels[x + y * self.width]
+    }
+
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> IndexedImage {
+        let mut pixels = Vec::new();
+
+        for y in y..y + height {
+            for x in x..x + width {
+                pixels.push(self.get(x, y));
+            }
+        }
+
+        IndexedImage {
+            width,
+            height,
+            pixels
+        }
+    }
+
+    // Cut into 8x8 tiles, padding the edges with transparent pixels
+    fn get_tiles(&self) -> (Vec<Sprite>, usize, usize) {
+        let tiles_wide = self.width.div_ceil(8);
+        let tiles_high = self.height.div_ceil(8);
+        let mut tiles = Vec::new();
+
+        for tile_y in 0..tiles_high {
+            for tile_x in 0..tiles_wide {
+                let mut pixels = Vec::new();
+
+                for y in 0..8 {
+                    for x in 0..8 {
+                        pixels.push(self.get(tile_x * 8 + x, tile_y * 8 + y));
+                    }
+                }
+
+                tiles.push(Sprite { pixels });
+            }
+        }
+
+        (tiles, tiles_wide, tiles_high)
+    }
+}
+
+// Hands out space for new tiles after the existing contents of a spritesheet,
+// keeping blocks rectangular in the 32 tile wide layout OAMs expect
+#[derive(Clone)]
+pub struct TileAllocator {
+    start: usize,
+    used: Vec<bool>
+}
+
+impl TileAllocator {
+    pub fn new(spritesheet: &Spritesheet) -> Self {
+        Self {
+            start: spritesheet.sprites.len().div_ceil(SHEET_WIDTH) * SHEET_WIDTH,
+            used: Vec::new()
+        }
+    }
+
+    // Tiles that were padded in but not handed out yet can't be reused
+    fn is_available(&self, tile: usize) -> bool {
+        tile < self.start || self.used.get(tile - self.start).copied().unwrap_or(false)
+    }
+
+    // None if the block would end past the last tile OAMs can point at
+    pub fn allocate(&mut self, spritesheet: &mut Spritesheet, width: usize, height: usize) -> Option<usize> {
+        let mut tile = self.start;
+
+        loop {
+            let fits = tile % SHEET_WIDTH + width <= SHEET_WIDTH && (0..height).all(|y| {
+                (0..width).all(|x| !self.used.get(tile + x + y * SHEET_WIDTH - self.start).copied().unwrap_or(false))
+            });
+
+            if fits {break;}
+            tile += 1;
+        }
+
+        let end = tile + (height - 1) * SHEET_WIDTH + width;
+        if end > MAX_TILE + 1 {return None;}
+
+        if self.used.len() < end - self.start {
+            self.used.resize(end - self.start, false);
+        }
+
+        while spritesheet.sprites.len() < end {
+            spritesheet.sprites.push(Sprite::blank());
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                self.used[tile + x + y * SHEET_WIDTH - self.start] = true;
+            }
+        }
+
+        Some(tile)
+    }
+}
+
+// The tiles an OAM would need in the sheet to show `block` with the given flip
+pub fn flip_block(block: &[Sprite], width: usize, height: usize, flip: OAMFlip) -> Vec<Sprite> {
+    let horizontal = matches!(flip, OAMFlip::Horizontal | OAMFlip::Both);
+    let vertical = matches!(flip, OAMFlip::Vertical | OAMFlip::Both);
+    let mut flipped = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let source_x = if horizontal { width - 1 - x } else { x };
+            let source_y = if vertical { height - 1 - y } else { y };
+            flipped.push(block[source_x + source_y * width].flipped(horizontal, vertical));
+        }
+    }
+
+    flipped
+}
+
+pub fn is_block_at(spritesheet: &Spritesheet, block: &[Sprite], width: usize, height: usize, tile: usize) -> bool {
+    if tile % SHEET_WIDTH + width > SHEET_WIDTH {return false;}
+
+    (0..height).all(|y| {
+        (0..width).all(|x| spritesheet.sprites.get(tile + x + y * SHEET_WIDTH) == Some(&block[x + y * width]))
+    })
+}
+
+// Finds where the block (or a flipped version of it) already is in the sheet
+pub fn find_block(spritesheet: &Spritesheet, allocator: &TileAllocator, block: &[Sprite], width: usize, height: usize, allow_flips: bool) -> Option<(usize, OAMFlip)> {
+    let flips = if allow_flips { &FLIPS[..] } else { &FLIPS[..1] };
+
+    for &flip in flips {
+        let flipped = flip_block(block, width, height, flip);
+
+        for tile in 0..spritesheet.sprites.len() {
+            let available = (0..height).all(|y| (0..width).all(|x| allocator.is_available(tile + x + y * SHEET_WIDTH)));
+
+            if available && is_block_at(spritesheet, &flipped, width, height, tile) {
+                return Some((tile, flip));
+            }
+        }
+    }
+
+    None
+}
+
+// Covers the non transparent tiles of the image with as few OAMs as it can, adding any
+// tiles that aren't in the sheet yet. `origin` is the position of the cel's (0, 0) in the image.
+// Neither the sheet nor the allocator are changed if the cel can't be made.
+pub fn fit_oams(image: &IndexedImage, origin: (i32, i32), palette: usize, spritesheet: &mut Spritesheet, allocator: &mut TileAllocator) -> Result<Vec<OAM>, String> {
+    let (tiles, tiles_wide, tiles_high) = image.get_tiles();
+
+    // Checked up front so the sheet isn't touched if the cel can't be made
+    let in_range = |position: i32| position >= i8::MIN as i32 && position <= i8::MAX as i32;
+    if !in_range(-origin.0) || !in_range(-origin.1)
+        || !in_range(((tiles_wide.max(1) - 1) * 8) as i32 - origin.0)
+        || !in_range(((tiles_high.max(1) - 1) * 8) as i32 - origin.1) {
+        return Err(String::from("The region is too far from the origin, OAM positions must be between -128 and 127"));
+    }
+
+    let mut new_spritesheet = spritesheet.clone();
+    let mut new_allocator = allocator.clone();
+    let mut covered = vec![false; tiles.len()];
+    let mut oams = Vec::new();
+
+    for tile_y in 0..tiles_high {
+        for tile_x in 0..tiles_wide {
+            let index = tile_x + tile_y * tiles_wide;
+            if covered[index] || tiles[index].is_blank() {continue;}
+
+            // Pick the shape that covers the most tiles, as long as at most half of it is empty
+            let mut best: Option<(OAMShape, OAMSize, usize, usize)> = None;
+            let mut best_filled = 0;
+
+            for (shape, size, width, height) in OAM_SHAPES {
+                if tile_x + width > tiles_wide || tile_y + height > tiles_high {continue;}
+
+                let block_tiles: Vec<usize> = (0..height)
+                    .flat_map(|y| (0..width).map(move |x| tile_x + x + (tile_y + y) * tiles_wide))
+                    .collect();
+
+                if block_tiles.iter().any(|&i| covered[i] && !tiles[i].is_blank()) {continue;}
+
+                let filled = block_tiles.iter().filter(|&&i| !tiles[i].is_blank()).count();
+
+                if filled * 2 >= width * height && filled > best_filled {
+                    best = Some((shape, size, width, height));
+                    best_filled = filled;
+                }
+            }
+
+            let (shape, size, width, height) = match best {
+                Some(best) => best,
+                None => continue
+            };
+
+            let mut block = Vec::new();
+            for y in 0..height {
+                for x in 0..width {
+                    let i = tile_x + x + (tile_y + y) * tiles_wide;
+                    covered[i] = true;
+                    block.push(tiles[i].clone());
+                }
+            }
+
+            let (tile, flip) = match find_block(&new_spritesheet, &new_allocator, &block, width, height, true) {
+                Some(found) => found,
+                None => {
+                    let tile = new_allocator.allocate(&mut new_spritesheet, width, height).ok_or_else(|| {
+                        format!("The spritesheet is full, OAMs can only use tiles 0x000 to 0x{:03x}", MAX_TILE)
+                    })?;
+
+                    for y in 0..height {
+                        for x in 0..width {
+                            new_spritesheet.sprites[tile + x + y * SHEET_WIDTH] = block[x + y * width].clone();
+                        }
+                    }
+
+                    (tile, OAMFlip::None)
+                }
+            };
+
+            oams.push(OAM {
+                shape,
+                size,
+                flip,
+                x: ((tile_x * 8) as i32 - origin.0) as i8,
+                y: ((tile_y * 8) as i32 - origin.1) as i8,
+                palette,
+                tile,
+                affine: false,
+                double_size: false,
+                affine_matrix: 0,
+                mode: OAMMode::Normal,
+                mosaic: false,
+                priority: 0,
+                hidden: false,
+                locked: false,
+                label: String::new(),
+                selected: false
+            });
+        }
+    }
+
+    *spritesheet = new_spritesheet;
+    *allocator = new_allocator;
+
+    Ok(oams)
+}
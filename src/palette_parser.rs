@@ -1,12 +1,29 @@
-use std::fs;
+use std::{fs, io::Write};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RGB {
     pub r: u8,
     pub g: u8,
     pub b: u8
 }
 
+impl RGB {
+    // Same conversions as gbagfx, so colors survive a round trip through the game's tools
+    pub fn from_bgr555(color: u16) -> RGB {
+        let upconvert = |channel: u16| ((channel & 0x1F) * 255 / 31) as u8;
+
+        RGB {
+            r: upconvert(color),
+            g: upconvert(color >> 5),
+            b: upconvert(color >> 10)
+        }
+    }
+
+    pub fn to_bgr555(self) -> u16 {
+        (self.r as u16 / 8) | ((self.g as u16 / 8) << 5) | ((self.b as u16 / 8) << 10)
+    }
+}
+
 pub struct Palette {
     pub palettes: Vec<Vec<RGB>>
 }
@@ -16,7 +33,10 @@ impl Palette {
         let bytes = fs::read(file_path)?;
         let mut palettes = Vec::new();
 
-        for pal in 0x0..0xF {
+        // Only read as many banks as the file actually has
+        let bank_count = (bytes.len().saturating_sub(0x18) / 0x40).min(0x10);
+
+        for pal in 0x0..bank_count {
             let mut colors: Vec<RGB> = Vec::new();
             
             for i in 0x0..0x10 {
@@ -36,4 +56,85 @@ impl Palette {
             palettes
         })
     }
-}
\ No newline at end of file
+
+    /*
+    RIFF palette format:
+
+    "RIFF", file size - 8 (4 bytes)
+    "PAL ", "data", data size (4 bytes)
+    Version 0x0300 (2 bytes), color count (2 bytes)
+    For each color: r, g, b, flags (always 0)
+    */
+    pub fn to_pal(&self, file_path: &str) -> Result<(), std::io::Error> {
+        let colors: Vec<&RGB> = self.palettes.iter().flatten().collect();
+        let data_size = (4 + colors.len() * 4) as u32;
+        let mut bytes: Vec<u8> = Vec::new();
+
+        bytes.extend(b"RIFF");
+        bytes.extend((data_size + 0x0C).to_le_bytes());
+        bytes.extend(b"PAL data");
+        bytes.extend(data_size.to_le_bytes());
+        bytes.extend(0x0300u16.to_le_bytes());
+        bytes.extend((colors.len() as u16).to_le_bytes());
+
+        for color in colors {
+            // Snapped so the .pal matches what the GBA would show
+            let color = RGB::from_bgr555(color.to_bgr555());
+            bytes.extend([color.r, color.g, color.b, 0x00]);
+        }
+
+        fs::File::create(file_path)?.write_all(&bytes)
+    }
+
+    // Raw little endian BGR555, the format the game's build includes
+    pub fn to_gbapal(&self, file_path: &str) -> Result<(), std::io::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        for color in self.palettes.iter().flatten() {
+            bytes.extend(color.to_bgr555().to_le_bytes());
+        }
+
+        fs::File::create(file_path)?.write_all(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("yanimator_{}_{}", std::process::id(), name)).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn pal_round_trip() {
+        let colors: Vec<RGB> = (0..0x20u16).map(|i| RGB::from_bgr555(i * 0x421)).collect();
+        let palette = Palette { palettes: vec![colors[..0x10].to_vec(), colors[0x10..].to_vec()] };
+
+        let path = get_temp_path("round_trip.pal");
+        palette.to_pal(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        let loaded = Palette::from_pal(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The RIFF size is everything after itself
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize, bytes.len() - 8);
+        assert_eq!(loaded.palettes, palette.palettes);
+    }
+
+    #[test]
+    fn pal_header_matches_reference() {
+        let reference_path = concat!(env!("CARGO_MANIFEST_DIR"), "/night_walk.pal");
+        let reference = fs::read(reference_path).unwrap();
+
+        let path = get_temp_path("reference.pal");
+        Palette::from_pal(reference_path).unwrap().to_pal(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes.len(), reference.len());
+        assert_eq!(bytes[..0x18], reference[..0x18]);
+    }
+}
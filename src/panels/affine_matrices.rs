@@ -0,0 +1,72 @@
+use egui::Ui;
+
+use crate::{anim_parser::AffineMatrix, Yanimator};
+
+// The GBA only has room for 32 sets of affine parameters
+pub const MAX_AFFINE_MATRICES: usize = 32;
+
+pub struct AffineMatricesPanel {
+    pub open: bool
+}
+
+impl AffineMatricesPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false
+        }
+    }
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    if app.affine_matrices.is_empty() {
+        ui.label("No matrices yet, affine OAMs will be drawn without rotation or scaling");
+    }
+
+    egui::Grid::new("affine_matrices_grid")
+        .num_columns(5)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("ID");
+            ui.label("Rotation");
+            ui.label("Scale");
+            ui.label("PA, PB, PC, PD");
+            ui.label("Used by");
+            ui.end_row();
+
+            for (i, matrix) in app.affine_matrices.iter_mut().enumerate() {
+                ui.label(i.to_string());
+
+                ui.add(egui::DragValue::new(&mut matrix.rotation).speed(1.0).range(-360.0..=360.0).suffix("°"));
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut matrix.scale_x).speed(0.01).range(0.01..=8.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut matrix.scale_y).speed(0.01).range(0.01..=8.0).prefix("y: "));
+                });
+
+                let parameters = matrix.get_parameters().map(|parameter| format!("0x{:04x}", parameter as u16));
+                ui.monospace(parameters.join(", "));
+
+                let uses = app.animation_cels
+                    .values()
+                    .flat_map(|cel| cel.oams.iter())
+                    .filter(|oam| oam.affine && oam.affine_matrix == i)
+                    .count();
+                ui.label(format!("{} OAMs", uses));
+
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.add_enabled(app.affine_matrices.len() < MAX_AFFINE_MATRICES, egui::Button::new("Add Matrix")).clicked() {
+            app.affine_matrices.push(AffineMatrix::identity());
+        }
+
+        // Only the last one can go, so OAMs don't end up pointing at a different matrix
+        if ui.add_enabled(!app.affine_matrices.is_empty(), egui::Button::new("Remove Last")).clicked() {
+            app.affine_matrices.pop();
+        }
+    });
+}
@@ -1,32 +1,58 @@
 use egui::{include_image, pos2, vec2, Button, Frame, Id, ImageButton, Modal, Rect, Scene, Sense, Ui};
 use itertools::Itertools;
+use regex::Regex;
 
-use crate::{anim_parser::AnimationCel, AppState, Yanimator};
+use crate::{anim_parser::{get_unique_name, AnimationCel}, AppState, Yanimator};
 
 pub struct AnimationCellsPanel {
     pub creation_modal_open: bool,
     deletion_confirmation_modal_open: bool,
+    rename_modal_open: bool,
+    duplicate_modal_open: bool,
     cell_name: String,
-    deleting_cell: Option<String>
+    deleting_cell: Option<String>,
+    renaming_cell: Option<String>,
+    duplicating_cell: Option<String>,
+    filter: String,
+    filter_is_regex: bool,
+    group_by_prefix: bool,
+    grid_view: bool
 }
 
+const THUMBNAIL_SIZE: f32 = 80.0;
+
 impl AnimationCellsPanel {
     pub fn init() -> Self {
         Self { 
             creation_modal_open: false,
             deletion_confirmation_modal_open: false,
+            rename_modal_open: false,
+            duplicate_modal_open: false,
             cell_name: String::from(""),
-            deleting_cell: None
+            deleting_cell: None,
+            renaming_cell: None,
+            duplicating_cell: None,
+            filter: String::new(),
+            filter_is_regex: false,
+            group_by_prefix: false,
+            grid_view: false
         }
     }
+
+    pub fn is_modal_open(&self) -> bool {
+        self.creation_modal_open || self.deletion_confirmation_modal_open || self.rename_modal_open || self.duplicate_modal_open
+    }
 }
 
 fn is_cell_name_invalid(app: &mut Yanimator) -> Option<String> {
-    let cell_name = &app.animation_cells_panel.cell_name;
-    
+    get_cell_name_error(app, &app.animation_cells_panel.cell_name)
+}
+
+pub fn get_cell_name_error(app: &Yanimator, cell_name: &str) -> Option<String> {
     if cell_name.len() == 0 {return None}
     if cell_name.contains(" ") {return Some(String::from("Cell name must not contain spaces"))}
     if !cell_name.chars().nth(0).unwrap().is_alphabetic() {return Some(String::from("First character in cell name must be a letter"))}
+    if !cell_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {return Some(String::from("Cell name must only contain letters, numbers and underscores"))}
     if app.animation_cels.get(cell_name).is_some() {return Some(String::from("Cell name has already been used"))}
 
     None
@@ -41,6 +67,59 @@ fn create_animation_cell(app: &mut Yanimator) {
     });
 }
 
+fn rename_animation_cell(app: &mut Yanimator) {
+    app.animation_cells_panel.rename_modal_open = false;
+
+    let old_name = match app.animation_cells_panel.renaming_cell.take() {
+        Some(name) => name,
+        None => return
+    };
+    let new_name = app.animation_cells_panel.cell_name.clone();
+
+    let mut cel = match app.animation_cels.remove(&old_name) {
+        Some(cel) => cel,
+        None => return
+    };
+
+    cel.name = new_name.clone();
+    app.animation_cels.insert(new_name.clone(), cel);
+
+    for animation in &mut app.animations {
+        for frame in &mut animation.frames {
+            if frame.cell == old_name {
+                frame.cell = new_name.clone();
+            }
+        }
+    }
+
+    for frame in &mut app.timeline.clipboard {
+        if frame.cell == old_name {
+            frame.cell = new_name.clone();
+        }
+    }
+
+    if app.editing_cell == old_name {
+        app.editing_cell = new_name;
+    }
+}
+
+fn duplicate_animation_cell(app: &mut Yanimator) {
+    app.animation_cells_panel.duplicate_modal_open = false;
+
+    if let Some(duplicating_cell) = app.animation_cells_panel.duplicating_cell.take() {
+        if let Some(cel) = app.animation_cels.get(&duplicating_cell) {
+            let mut duplicate = cel.clone();
+            duplicate.name = app.animation_cells_panel.cell_name.clone();
+
+            for oam in &mut duplicate.oams {
+                oam.selected = false;
+            }
+
+            app.animation_cels.insert(duplicate.name.clone(), duplicate);
+        }
+    }
+}
+
 fn remove_animation_cell(app: &mut Yanimator) {
     if let Some(deleting_cell) = &app.animation_cells_panel.deleting_cell {
         app.animation_cels.remove(deleting_cell);
@@ -61,6 +140,183 @@ fn remove_animation_cell(app: &mut Yanimator) {
     }
 }
 
+enum CelFilter {
+    None,
+    Text(String),
+    Regex(Regex)
+}
+
+impl CelFilter {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            CelFilter::None => true,
+            CelFilter::Text(text) => name.to_lowercase().contains(text),
+            CelFilter::Regex(regex) => regex.is_match(name)
+        }
+    }
+}
+
+fn get_cel_filter(app: &Yanimator) -> Result<CelFilter, String> {
+    let filter = &app.animation_cells_panel.filter;
+
+    if filter.is_empty() {
+        return Ok(CelFilter::None);
+    }
+
+    if app.animation_cells_panel.filter_is_regex {
+        match Regex::new(filter) {
+            Ok(regex) => Ok(CelFilter::Regex(regex)),
+            Err(_) => Err(String::from("Invalid regex"))
+        }
+    } else {
+        Ok(CelFilter::Text(filter.to_lowercase()))
+    }
+}
+
+// Scenes name their cels like anim_scene_name, so everything before the last underscore
+fn get_name_prefix(name: &str) -> &str {
+    match name.rfind('_') {
+        Some(i) if i > 0 => &name[..i],
+        _ => name
+    }
+}
+
+// Cels used by the current animation in the order they appear, then the rest alphabetically
+fn get_sorted_cel_names(app: &Yanimator, filter: &CelFilter) -> (Vec<String>, Vec<String>) {
+    let mut used_cels: Vec<String> = Vec::new();
+
+    if let Some(animation) = app.animations.get(app.animation_id) {
+        used_cels = animation.get_used_cels()
+            .into_iter()
+            .filter(|&name| app.animation_cels.contains_key(name) && filter.matches(name))
+            .cloned()
+            .collect();
+    }
+
+    let other_cels = app.animation_cels
+        .keys()
+        .filter(|&name| !used_cels.contains(name) && filter.matches(name))
+        .sorted()
+        .cloned()
+        .collect();
+
+    (used_cels, other_cels)
+}
+
+fn show_cels(ui: &mut Ui, app: &mut Yanimator, names: &[String]) {
+    if app.animation_cells_panel.grid_view {
+        ui.horizontal_wrapped(|ui| {
+            for name in names {
+                cel_thumbnail(ui, app, name);
+            }
+        });
+    } else {
+        for name in names {
+            cel_button(ui, app, name);
+        }
+    }
+}
+
+fn cel_context_menu(ui: &mut Ui, app: &mut Yanimator, name: &str) {
+    if ui.add(Button::image_and_text(include_image!("../../assets/edit.png"), "Edit")).clicked() {
+        app.state = AppState::CellEditor;
+        app.edit_oam(name, 0);
+        ui.close_menu();
+    }
+
+    if ui.add(Button::image_and_text(include_image!("../../assets/edit.png"), "Rename")).clicked() {
+        app.animation_cells_panel.renaming_cell = Some(String::from(name));
+        app.animation_cells_panel.cell_name = String::from(name);
+        app.animation_cells_panel.rename_modal_open = true;
+        ui.close_menu();
+    }
+
+    if ui.add(Button::image_and_text(include_image!("../../assets/add.png"), "Duplicate")).clicked() {
+        app.animation_cells_panel.duplicating_cell = Some(String::from(name));
+        app.animation_cells_panel.cell_name = get_unique_name(name, |n| app.animation_cels.contains_key(n));
+        app.animation_cells_panel.duplicate_modal_open = true;
+        ui.close_menu();
+    }
+
+    if ui.add(Button::image_and_text(include_image!("../../assets/delete.png"), "Delete")).clicked() {
+        app.animation_cells_panel.deleting_cell = Some(String::from(name));
+        app.animation_cells_panel.deletion_confirmation_modal_open = true;
+        ui.close_menu();
+    }
+
+    if ui.add(Button::image_and_text(include_image!("../../assets/keyframe_add.png"), "Insert Keyframe")).clicked() {
+        if let Some(animation) = app.animations.get_mut(app.animation_id) {
+            animation.insert_anim_frame(String::from(name), app.frames as isize);
+        }
+
+        ui.close_menu();
+    }
+}
+
+fn cel_button(ui: &mut Ui, app: &mut Yanimator, name: &str) {
+    ui.horizontal(|ui| {
+        let button = ui.add(Button::new(name).min_size(vec2(ui.available_width(), 20.0)));
+        
+        if button.double_clicked() {
+            app.state = AppState::CellEditor;
+            app.edit_oam(name, 0);
+        }
+
+        button.context_menu(|ui| {
+            cel_context_menu(ui, app, name);
+        });
+
+        if let Some(cel) = app.animation_cels.get(name) {
+            button.on_hover_ui_at_pointer(|ui| {    
+                ui.allocate_ui(vec2(100.0, 100.0), |ui| {
+                    let mut rect = Rect::ZERO;                    
+                    Scene::default()
+                        .zoom_range(0.5..=0.5)
+                        .show(ui, &mut rect, |ui| {
+                            cel.draw(&app.textures, &app.affine_matrices, &app.viewport.effects, ui, false);
+                        });
+                });
+            });
+        }
+    });
+}
+
+fn cel_thumbnail(ui: &mut Ui, app: &mut Yanimator, name: &str) {
+    let cel = match app.animation_cels.get(name) {
+        Some(cel) => cel,
+        None => return
+    };
+
+    let response = ui.vertical(|ui| {
+        ui.set_width(THUMBNAIL_SIZE);
+
+        let scene = egui::Frame::canvas(ui.style()).show(ui, |ui| {
+            ui.allocate_ui(vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE), |ui| {
+                // Reset every frame so the thumbnail can't be panned
+                let mut rect = Rect::ZERO;
+                Scene::default()
+                    .zoom_range(0.4..=0.4)
+                    .show(ui, &mut rect, |ui| {
+                        cel.draw(&app.textures, &app.affine_matrices, &app.viewport.effects, ui, false);
+                    }).response
+            }).inner
+        }).inner;
+
+        ui.add(egui::Label::new(egui::RichText::new(name).small()).truncate());
+
+        scene
+    }).inner;
+
+    if response.double_clicked() {
+        app.state = AppState::CellEditor;
+        app.edit_oam(name, 0);
+    }
+
+    response.on_hover_text(name).context_menu(|ui| {
+        cel_context_menu(ui, app, name);
+    });
+}
+
 pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
     ui.horizontal(|ui| {
         ui.heading("Animation Cells");
@@ -73,105 +329,54 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
         });
     });
     
-    ui.separator();
-    
-    // i am not good enough at rust to deal with borrow checker issues like these,,,
-    // i might try and fix this later but i've literally been banging my head on the wall 
-    // for like 3 hours because of this
-
-    /*let mut sorted_cels = Vec::new();
-    
+    ui.horizontal(|ui| {
+        let filter_hint = if app.animation_cells_panel.filter_is_regex { "Filter (regex)" } else { "Filter" };
+        ui.add(egui::TextEdit::singleline(&mut app.animation_cells_panel.filter).hint_text(filter_hint).desired_width(120.0));
 
-    let mut used_cels = Vec::new();
-    {
-        let animation = app.animations.get(app.animation_id);
+        ui.toggle_value(&mut app.animation_cells_panel.filter_is_regex, ".*").on_hover_text("Use regex");
+        ui.toggle_value(&mut app.animation_cells_panel.group_by_prefix, "Group").on_hover_text("Group cels by name prefix");
+        ui.toggle_value(&mut app.animation_cells_panel.grid_view, "Grid").on_hover_text("Show thumbnails");
+    });
 
-        if let Some(animation) = animation {
-            used_cels = animation.get_used_cels();
+    let filter = match get_cel_filter(app) {
+        Ok(filter) => filter,
+        Err(message) => {
+            ui.colored_label(ui.visuals().error_fg_color, message);
+            CelFilter::None
         }
-    }
-    
-    sorted_cels.append(&mut used_cels);
+    };
 
-    let animation_cels_keys: Vec<_> = app.animation_cels.keys().clone().sorted().collect();
+    ui.separator();
 
-    for cel in animation_cels_keys {
-        if !sorted_cels.iter().any(|&c| c == cel) {
-            sorted_cels.push(cel);
-        }
-    }*/
+    let (used_cels, other_cels) = get_sorted_cel_names(app, &filter);
     
     egui::ScrollArea::vertical()
     .show(ui, |ui| {
-        /*egui::Grid::new("animation_cells")
-        .num_columns(1)
-        .striped(true)
-        .spacing([40.0, 4.0])
-        .show(ui, |ui| {*/
-            for name in app.animation_cels.keys().sorted() {
-                let cel = app.animation_cels.get(name).unwrap();
+        if !used_cels.is_empty() {
+            if let Some(animation) = app.animations.get(app.animation_id) {
+                ui.label(format!("Used in {}", animation.name));
+            }
 
-                ui.horizontal(|ui| {
-                    let button = ui.add(Button::new(name).min_size(vec2(ui.available_width(), 20.0)));
-                    
-                    if button.double_clicked() {
-                        app.state = AppState::CellEditor;
-                        app.editing_cell = String::from(name);
-                    }
+            show_cels(ui, app, &used_cels);
+            ui.separator();
+        }
 
-                    button.context_menu(|ui| {
-                        if ui.add(Button::image_and_text(include_image!("../../assets/edit.png"), "Edit")).clicked() {
-                            app.state = AppState::CellEditor;
-                            app.editing_cell = String::from(name);
-                            ui.close_menu();
-                        }
-
-                        if ui.add(Button::image_and_text(include_image!("../../assets/delete.png"), "Delete")).clicked() {
-                            app.animation_cells_panel.deleting_cell = Some(String::from(name));
-                            app.animation_cells_panel.deletion_confirmation_modal_open = true;
-                            ui.close_menu();
-                        }
-
-                        if ui.add(Button::image_and_text(include_image!("../../assets/keyframe_add.png"), "Insert Keyframe")).clicked() {
-                            if let Some(animation) = app.animations.get_mut(app.animation_id) {
-                                animation.insert_anim_frame(String::from(name), app.frames as isize);
-                            }
-
-                            ui.close_menu();
-                        }
-                    });
+        if app.animation_cells_panel.group_by_prefix {
+            let mut other_cels = other_cels;
+            other_cels.sort_by(|a, b| (get_name_prefix(a), a).cmp(&(get_name_prefix(b), b)));
 
-                    button.on_hover_ui_at_pointer(|ui| {    
-                        ui.allocate_ui(vec2(100.0, 100.0), |ui| {
-                            let mut rect = Rect::ZERO;                    
-                            Scene::default()
-                                .zoom_range(0.5..=0.5)
-                                .show(ui, &mut rect, |ui| {
-                                    cel.draw(&app.textures, ui, false);
-                                });
-                        });
-                    });
+            for (prefix, names) in &other_cels.into_iter().chunk_by(|name| String::from(get_name_prefix(name))) {
+                let names: Vec<String> = names.collect();
 
-                    /*ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.add_sized(vec2(20.0, 20.0), ImageButton::new(include_image!("../../assets/edit.png"))).clicked() {
-                           
-                        }
-                        
-                        if ui.add_sized(vec2(20.0, 20.0), ImageButton::new(include_image!("../../assets/keyframe_add.png"))).clicked() {
-                            if let Some(animation) = app.animations.get_mut(app.animation_id) {
-                                animation.insert_anim_frame(String::from(name), app.frames as isize);
-                            }
-                        }
-
-                        if ui.add_sized(vec2(20.0, 20.0), ImageButton::new(include_image!("../../assets/delete.png"))).clicked() {
-                            app.animation_cells_panel.deleting_cell = Some(String::from(name));
-                            app.animation_cells_panel.deletion_confirmation_modal_open = true;
-                        }
-                    });*/
-                });
+                egui::CollapsingHeader::new(format!("{} ({})", prefix, names.len()))
+                    .id_salt(("cel_group", &prefix))
+                    .show(ui, |ui| {
+                        show_cels(ui, app, &names);
+                    });
             }
-        //});
-        
+        } else {
+            show_cels(ui, app, &other_cels);
+        }
     });
 
     let rect = egui::Rect::from_min_size(
@@ -223,6 +428,54 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
                 });
             }
         });
+    } else if app.animation_cells_panel.rename_modal_open {
+        Modal::new(Id::new("animation_cell_rename")).show(ui.ctx(), |ui| {
+            if let Some(renaming_cell) = &app.animation_cells_panel.renaming_cell {
+                ui.heading(format!("Renaming {}", renaming_cell));
+                ui.separator();
+
+                ui.label("New Cell Name:");
+                let field = ui.text_edit_singleline(&mut app.animation_cells_panel.cell_name);
+
+                if let Some(message) = is_cell_name_invalid(app) {
+                    field.show_tooltip_text(message);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Cancel").clicked() {
+                        app.animation_cells_panel.rename_modal_open = false;
+                    }
+
+                    if ui.button("Rename").clicked() && !app.animation_cells_panel.cell_name.is_empty() && is_cell_name_invalid(app).is_none() {
+                        rename_animation_cell(app);
+                    }
+                });
+            }
+        });
+    } else if app.animation_cells_panel.duplicate_modal_open {
+        Modal::new(Id::new("animation_cell_duplicate")).show(ui.ctx(), |ui| {
+            if let Some(duplicating_cell) = &app.animation_cells_panel.duplicating_cell {
+                ui.heading(format!("Duplicating {}", duplicating_cell));
+                ui.separator();
+
+                ui.label("Cell Name:");
+                let field = ui.text_edit_singleline(&mut app.animation_cells_panel.cell_name);
+
+                if let Some(message) = is_cell_name_invalid(app) {
+                    field.show_tooltip_text(message);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Cancel").clicked() {
+                        app.animation_cells_panel.duplicate_modal_open = false;
+                    }
+
+                    if ui.button("Duplicate").clicked() && !app.animation_cells_panel.cell_name.is_empty() && is_cell_name_invalid(app).is_none() {
+                        duplicate_animation_cell(app);
+                    }
+                });
+            }
+        });
     }
     
     ui.allocate_rect(rect, egui::Sense::hover());
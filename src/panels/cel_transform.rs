@@ -0,0 +1,121 @@
+use egui::Ui;
+
+use crate::{anim_parser::{AnimationCel, CelAnchor}, Yanimator};
+
+pub struct CelTransformPanel {
+    pub open: bool,
+    // The mirror axis, (0, 0) being the cel's origin
+    axis_x: i32,
+    axis_y: i32,
+    offset_x: i32,
+    offset_y: i32,
+    message: Option<String>
+}
+
+impl CelTransformPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            axis_x: 0,
+            axis_y: 0,
+            offset_x: 0,
+            offset_y: 0,
+            message: None
+        }
+    }
+}
+
+// Runs a transform on the cel being edited, showing why it failed if it did
+pub fn transform_cel(app: &mut Yanimator, transform: impl FnOnce(&mut AnimationCel) -> Result<(), String>) {
+    let cel = match app.animation_cels.get_mut(&app.editing_cell) {
+        Some(cel) => cel,
+        None => return
+    };
+
+    app.cel_transform_panel.message = match transform(cel) {
+        Ok(()) => None,
+        Err(message) => {
+            // The panel might be closed when this comes from the menu
+            app.cel_transform_panel.open = true;
+            Some(message)
+        }
+    };
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    let bounds = match app.animation_cels.get(&app.editing_cell) {
+        Some(cel) => cel.get_bounds(),
+        None => {
+            ui.label("Open a cel to transform it");
+            return;
+        }
+    };
+
+    ui.label(format!("Editing {}", app.editing_cell));
+
+    if let Some((left, top, right, bottom)) = bounds {
+        ui.weak(format!("Bounds: ({}, {}) to ({}, {})", left, top, right, bottom));
+    }
+
+    let panel = &mut app.cel_transform_panel;
+
+    egui::Grid::new("cel_transform_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Mirror Axis");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut panel.axis_x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut panel.axis_y).prefix("y: "));
+
+                if ui.button("Origin").clicked() {
+                    panel.axis_x = 0;
+                    panel.axis_y = 0;
+                }
+            });
+            ui.end_row();
+
+            ui.label("Offset");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut panel.offset_x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut panel.offset_y).prefix("y: "));
+            });
+            ui.end_row();
+        });
+
+    let axis = (panel.axis_x, panel.axis_y);
+    let offset = (panel.offset_x, panel.offset_y);
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.button("Mirror Horizontally").clicked() {
+            transform_cel(app, |cel| cel.mirror(true, false, axis));
+        }
+
+        if ui.button("Mirror Vertically").clicked() {
+            transform_cel(app, |cel| cel.mirror(false, true, axis));
+        }
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Translate").clicked() {
+            transform_cel(app, |cel| cel.translate(offset.0, offset.1));
+        }
+
+        if ui.button("Anchor to Center").clicked() {
+            transform_cel(app, |cel| cel.anchor(CelAnchor::Center));
+        }
+
+        if ui.button("Anchor to Bottom").clicked() {
+            transform_cel(app, |cel| cel.anchor(CelAnchor::Bottom));
+        }
+    });
+
+    if app.animation_cels.get(&app.editing_cell).is_some_and(|cel| cel.oams.iter().any(|oam| oam.affine)) {
+        ui.weak("Affine OAMs are moved when mirroring, but their matrix has to be flipped by hand");
+    }
+
+    if let Some(message) = &app.cel_transform_panel.message {
+        ui.colored_label(ui.visuals().error_fg_color, message);
+    }
+}
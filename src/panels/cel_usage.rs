@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use egui::{vec2, Id, Modal, Ui};
+use itertools::Itertools;
+
+use crate::{AppState, Yanimator};
+
+#[derive(PartialEq)]
+pub enum CelUsageTab {
+    ByCel,
+    ByAnimation,
+    Unused
+}
+
+pub struct CelUsagePanel {
+    pub open: bool,
+    pub tab: CelUsageTab,
+    deletion_confirmation_modal_open: bool
+}
+
+impl CelUsagePanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            tab: CelUsageTab::ByCel,
+            deletion_confirmation_modal_open: false
+        }
+    }
+
+    pub fn is_modal_open(&self) -> bool {
+        self.deletion_confirmation_modal_open
+    }
+}
+
+fn get_unused_cels(app: &Yanimator) -> Vec<String> {
+    let used_cels: HashSet<&String> = app.animations.iter().flat_map(|animation| animation.get_used_cels()).collect();
+
+    app.animation_cels
+        .keys()
+        .filter(|&name| !used_cels.contains(name))
+        .sorted()
+        .cloned()
+        .collect()
+}
+
+fn remove_unused_cels(app: &mut Yanimator) {
+    for name in get_unused_cels(app) {
+        app.animation_cels.remove(&name);
+    }
+
+    if !app.animation_cels.contains_key(&app.editing_cell) {
+        app.edit_oam("", 0);
+    }
+}
+
+fn go_to_frame(app: &mut Yanimator, animation_id: usize, position: usize) {
+    app.state = AppState::AnimationEditor;
+    app.animation_id = animation_id;
+    app.frames = position;
+    app.timeline.keyframes.clear();
+}
+
+fn edit_cel(app: &mut Yanimator, name: &str) {
+    app.state = AppState::CellEditor;
+    app.edit_oam(name, 0);
+}
+
+fn ui_by_cel(ui: &mut Ui, app: &mut Yanimator) {
+    let cel_names: Vec<String> = app.animation_cels.keys().sorted().cloned().collect();
+
+    for name in cel_names {
+        let usages: Vec<(usize, Vec<(usize, usize)>)> = app.animations
+            .iter()
+            .enumerate()
+            .map(|(i, animation)| (i, animation.get_cel_usages(&name)))
+            .filter(|(_, usages)| !usages.is_empty())
+            .collect();
+
+        let frame_count: usize = usages.iter().map(|(_, frames)| frames.len()).sum();
+
+        egui::CollapsingHeader::new(format!("{} ({} frames)", name, frame_count))
+            .id_salt(("cel_usage", &name))
+            .show(ui, |ui| {
+                if ui.link("Edit cel").clicked() {
+                    edit_cel(app, &name);
+                }
+
+                if usages.is_empty() {
+                    ui.label("Not used by any animation");
+                }
+
+                for (animation_id, frames) in usages {
+                    let animation_name = app.animations[animation_id].name.clone();
+
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(format!("{}:", animation_name));
+
+                        for (frame_index, position) in frames {
+                            if ui.link(format!("#{} (frame {})", frame_index, position)).clicked() {
+                                go_to_frame(app, animation_id, position);
+                            }
+                        }
+                    });
+                }
+            });
+    }
+}
+
+fn ui_by_animation(ui: &mut Ui, app: &mut Yanimator) {
+    for animation_id in 0..app.animations.len() {
+        let animation = &app.animations[animation_id];
+        let used_cels: Vec<String> = animation.get_used_cels().into_iter().cloned().collect();
+
+        egui::CollapsingHeader::new(format!("{} ({} cels)", animation.name, used_cels.len()))
+            .id_salt(("animation_usage", animation_id))
+            .show(ui, |ui| {
+                if ui.link("Open animation").clicked() {
+                    go_to_frame(app, animation_id, 0);
+                }
+
+                for name in used_cels {
+                    if !app.animation_cels.contains_key(&name) {
+                        ui.label(format!("{} (missing)", name));
+                    } else if ui.link(&name).clicked() {
+                        edit_cel(app, &name);
+                    }
+                }
+            });
+    }
+}
+
+fn ui_unused(ui: &mut Ui, app: &mut Yanimator) {
+    let unused_cels = get_unused_cels(app);
+
+    if unused_cels.is_empty() {
+        ui.label("Every cel is used by an animation");
+        return;
+    }
+
+    ui.label(format!("{} cels aren't used by any animation:", unused_cels.len()));
+
+    for name in &unused_cels {
+        if ui.link(name).clicked() {
+            edit_cel(app, name);
+        }
+    }
+
+    ui.separator();
+
+    if ui.button("Delete Unused Cels").clicked() {
+        app.cel_usage_panel.deletion_confirmation_modal_open = true;
+    }
+
+    if app.cel_usage_panel.deletion_confirmation_modal_open {
+        Modal::new(Id::new("unused_cels_deletion")).show(ui.ctx(), |ui| {
+            ui.heading("Confirm Deletion");
+            let separator = ui.separator();
+
+            ui.label(format!("Are you sure you want to delete {} unused cels?", unused_cels.len()));
+
+            ui.allocate_ui_with_layout(vec2(separator.rect.width(), ui.available_height()), egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Delete").clicked() {
+                    remove_unused_cels(app);
+                    app.cel_usage_panel.deletion_confirmation_modal_open = false;
+                }
+
+                if ui.button("Cancel").clicked() {
+                    app.cel_usage_panel.deletion_confirmation_modal_open = false;
+                }
+            });
+        });
+    }
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut app.cel_usage_panel.tab, CelUsageTab::ByCel, "By Cel");
+        ui.selectable_value(&mut app.cel_usage_panel.tab, CelUsageTab::ByAnimation, "By Animation");
+        ui.selectable_value(&mut app.cel_usage_panel.tab, CelUsageTab::Unused, "Unused Cels");
+    });
+
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+    .show(ui, |ui| {
+        match app.cel_usage_panel.tab {
+            CelUsageTab::ByCel => ui_by_cel(ui, app),
+            CelUsageTab::ByAnimation => ui_by_animation(ui, app),
+            CelUsageTab::Unused => ui_unused(ui, app)
+        }
+    });
+}
@@ -3,12 +3,19 @@ use std::{path::PathBuf};
 use egui::{include_image, menu, Button, ColorImage, Key, KeyboardShortcut, Modifiers, TextureHandle, Ui};
 
 
-use crate::{export, import, palette_parser::Palette, sprite_parser::Spritesheet, Yanimator};
+use crate::{anim_parser::CelAnchor, export, import, palette_parser::{Palette, RGB}, panels::{cel_transform, cel_usage::CelUsageTab, oams, timeline}, sprite_parser::{Sprite, Spritesheet}, AppState, ProjectStructure, Yanimator};
 use rfd::FileDialog;
 
 const NEW_PROJECT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::N);
 const OPEN_PROJECT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::O);
 const SAVE_PROJECT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::S);
+const COPY: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::C);
+const CUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::X);
+const PASTE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::V);
+const DUPLICATE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::D);
+const SELECT_ALL: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::A);
+const INVERT_SELECTION: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::I);
+const RESET_VIEWPORT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::Num0);
 
 fn open_project(app: &mut Yanimator) {
     let file_path: PathBuf = match FileDialog::new()
@@ -25,9 +32,12 @@ fn open_project(app: &mut Yanimator) {
         None => return
     };
     
-    if let Ok((animation_cels, animations)) = import::load_project_json(path_str) {
-        app.animations = animations;
-        app.animation_cels = animation_cels;
+    if let Ok(project) = import::load_project_json(path_str) {
+        app.animations = project.animations;
+        app.animation_cels = project.animation_cels;
+        app.viewport.reference_image = project.reference_image;
+        app.viewport.backdrop = project.backdrop;
+        app.affine_matrices = project.affine_matrices;
     }
 
     //let (animation_cels, animations) = import::load_project(path_str);
@@ -50,7 +60,21 @@ fn save_project(app: &mut Yanimator) {
         None => return
     };
     
-    crate::export::create_project_json(path_str, &app.animation_cels, &app.animations);
+    let project = ProjectStructure {
+        animation_cels: app.animation_cels.clone(),
+        animations: app.animations.clone(),
+        reference_image: app.viewport.reference_image.clone(),
+        backdrop: app.viewport.backdrop.clone(),
+        affine_matrices: app.affine_matrices.clone()
+    };
+
+    if let Err(err) = crate::export::create_project_json(path_str, &project) {
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Error)
+            .set_title("Couldn't save project")
+            .set_description(err.to_string())
+            .show();
+    }
 }
 
 fn load_palette(ui: &mut Ui, app: &mut Yanimator) {
@@ -91,7 +115,37 @@ fn load_spritesheet(ui: &mut Ui, app: &mut Yanimator) {
     load_texture_handles(ui, app);
 }
 
-fn load_texture_handles(ui: &mut Ui, app: &mut Yanimator) {
+const SPRITE_TEXTURE_OPTIONS: egui::TextureOptions = egui::TextureOptions {
+    magnification: egui::TextureFilter::Nearest,
+    minification: egui::TextureFilter::Nearest,
+    wrap_mode: egui::TextureWrapMode::Repeat,
+    mipmap_mode: None,
+};
+
+fn get_sprite_image(sprite: &Sprite, pal: &[RGB]) -> ColorImage {
+    let mut pixels: Vec<u8> = Vec::new();
+
+    for i in 0..0x40 {
+        let palette_id = sprite.pixels[i];
+        
+        if palette_id == 0 {
+            pixels.push(0);
+            pixels.push(0);
+            pixels.push(0);
+            pixels.push(0);
+        } else {
+            let rgb = &pal[palette_id as usize];
+            pixels.push(rgb.r);
+            pixels.push(rgb.g);
+            pixels.push(rgb.b);
+            pixels.push(255);
+        }
+    }
+
+    ColorImage::from_rgba_unmultiplied([8, 8], &pixels)
+}
+
+pub fn load_texture_handles(ui: &mut Ui, app: &mut Yanimator) {
     let mut textures: Vec<Vec<TextureHandle>> = Vec::new();
     
     if app.palette.palettes.len() == 0 {return;}
@@ -102,35 +156,12 @@ fn load_texture_handles(ui: &mut Ui, app: &mut Yanimator) {
 
         for i in 0..app.spritesheet.sprites.len() {
             let sprite = &app.spritesheet.sprites[i];
-            let mut pixels: Vec<u8> = Vec::new();
-        
-            for i in 0..0x40 {
-                let palette_id = sprite.pixels[i];
-                
-                if palette_id == 0 {
-                    pixels.push(0);
-                    pixels.push(0);
-                    pixels.push(0);
-                    pixels.push(0);
-                } else {
-                    let rgb = &pal[palette_id as usize];
-                    pixels.push(rgb.r);
-                    pixels.push(rgb.g);
-                    pixels.push(rgb.b);
-                    pixels.push(255);
-                }
-            }
             
             palette_textures.push(
                 ui.ctx().load_texture(
                 i.to_string(),
-                ColorImage::from_rgba_unmultiplied([8, 8], &pixels), 
-                egui::TextureOptions {
-                    magnification: egui::TextureFilter::Nearest,
-                    minification: egui::TextureFilter::Nearest,
-                    wrap_mode: egui::TextureWrapMode::Repeat,
-                    mipmap_mode: None,
-                })
+                get_sprite_image(sprite, pal), 
+                SPRITE_TEXTURE_OPTIONS)
             )
         }
 
@@ -140,6 +171,37 @@ fn load_texture_handles(ui: &mut Ui, app: &mut Yanimator) {
     app.textures = textures;
 }
 
+// Redraws one tile in every palette bank after its pixels change
+pub fn update_sprite_textures(app: &mut Yanimator, sprite_id: usize) {
+    let sprite = match app.spritesheet.sprites.get(sprite_id) {
+        Some(sprite) => sprite,
+        None => return
+    };
+
+    for (pal, palette_textures) in app.palette.palettes.iter().zip(app.textures.iter_mut()) {
+        if let Some(texture) = palette_textures.get_mut(sprite_id) {
+            texture.set(get_sprite_image(sprite, pal), SPRITE_TEXTURE_OPTIONS);
+        }
+    }
+}
+
+// Redraws the already loaded textures of one palette bank after its colors change
+pub fn update_palette_textures(app: &mut Yanimator, palette_id: usize) {
+    let pal = match app.palette.palettes.get(palette_id) {
+        Some(pal) => pal,
+        None => return
+    };
+
+    let palette_textures = match app.textures.get_mut(palette_id) {
+        Some(palette_textures) => palette_textures,
+        None => return
+    };
+
+    for (sprite, texture) in app.spritesheet.sprites.iter().zip(palette_textures.iter_mut()) {
+        texture.set(get_sprite_image(sprite, pal), SPRITE_TEXTURE_OPTIONS);
+    }
+}
+
 fn load_animation_cels(app: &mut Yanimator) {
     let file_path: PathBuf = match FileDialog::new()
     .add_filter("C", &["c"])
@@ -217,6 +279,10 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
         open_project(app);
     }
 
+    if ui.input_mut(|i| i.consume_shortcut(&RESET_VIEWPORT)) {
+        app.viewport.reset_view();
+    }
+
     if ui.input_mut(|i| i.consume_shortcut(&SAVE_PROJECT)) {
         open_project(app);
     }
@@ -253,6 +319,11 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
                 load_animations(app);
             }
 
+            if ui.add(Button::image_and_text(include_image!("../../assets/television.png"), "Import PNG Sequence...")).clicked() {
+                app.sequence_importer_panel.open = true;
+                ui.close_menu();
+            }
+
             ui.separator();
 
             if ui.add(Button::image_and_text(include_image!("../../assets/film_save.png"), "Export Animation Cels (.c)")).clicked() {
@@ -263,5 +334,142 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
                 export_animations(app);
             }
         });
+
+        ui.menu_button("Edit", |ui| {
+            ui.add_enabled_ui(app.state == AppState::AnimationEditor, |ui| {
+                if ui.add(Button::new("Copy Keyframes").shortcut_text(ui.ctx().format_shortcut(&COPY))).clicked() {
+                    timeline::copy_keyframes(app);
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Cut Keyframes").shortcut_text(ui.ctx().format_shortcut(&CUT))).clicked() {
+                    timeline::cut_keyframes(app);
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Paste Keyframes").shortcut_text(ui.ctx().format_shortcut(&PASTE))).clicked() {
+                    timeline::paste_keyframes(app);
+                    ui.close_menu();
+                }
+
+                ui.separator();
+
+                if ui.add(Button::new("Select All Keyframes").shortcut_text(ui.ctx().format_shortcut(&SELECT_ALL))).clicked() {
+                    timeline::select_all_keyframes(app);
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Invert Keyframe Selection").shortcut_text(ui.ctx().format_shortcut(&INVERT_SELECTION))).clicked() {
+                    timeline::invert_keyframe_selection(app);
+                    ui.close_menu();
+                }
+            });
+
+            ui.separator();
+
+            ui.add_enabled_ui(app.state == AppState::CellEditor, |ui| {
+                if ui.add(Button::new("Copy OAMs").shortcut_text(ui.ctx().format_shortcut(&COPY))).clicked() {
+                    oams::copy_oams(app);
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Cut OAMs").shortcut_text(ui.ctx().format_shortcut(&CUT))).clicked() {
+                    oams::cut_oams(app);
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Paste OAMs").shortcut_text(ui.ctx().format_shortcut(&PASTE))).clicked() {
+                    oams::paste_oams(app, false);
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Duplicate OAMs").shortcut_text(ui.ctx().format_shortcut(&DUPLICATE))).clicked() {
+                    oams::duplicate_oams(app);
+                    ui.close_menu();
+                }
+            });
+
+            ui.separator();
+
+            if ui.add(Button::image_and_text(include_image!("../../assets/picture_add.png"), "Generate Cel from Image...")).clicked() {
+                app.oam_generator_panel.open = true;
+                ui.close_menu();
+            }
+
+            if ui.add(Button::image_and_text(include_image!("../../assets/picture_add.png"), "Optimize Spritesheet...")).clicked() {
+                app.spritesheet_optimizer_panel.open = true;
+                ui.close_menu();
+            }
+
+            if ui.add(Button::new("Remap Tiles and Palettes...")).clicked() {
+                app.remap_panel.open = true;
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            ui.add_enabled_ui(app.state == AppState::CellEditor, |ui| {
+                if ui.add(Button::new("Mirror Cel Horizontally")).clicked() {
+                    cel_transform::transform_cel(app, |cel| cel.mirror(true, false, (0, 0)));
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Mirror Cel Vertically")).clicked() {
+                    cel_transform::transform_cel(app, |cel| cel.mirror(false, true, (0, 0)));
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Anchor Cel to Center")).clicked() {
+                    cel_transform::transform_cel(app, |cel| cel.anchor(CelAnchor::Center));
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Anchor Cel to Bottom")).clicked() {
+                    cel_transform::transform_cel(app, |cel| cel.anchor(CelAnchor::Bottom));
+                    ui.close_menu();
+                }
+
+                if ui.add(Button::new("Transform Cel...")).clicked() {
+                    app.cel_transform_panel.open = true;
+                    ui.close_menu();
+                }
+            });
+        });
+
+        ui.menu_button("View", |ui| {
+            if ui.add(Button::image_and_text(include_image!("../../assets/film.png"), "Cel Usage")).clicked() {
+                app.cel_usage_panel.open = true;
+                app.cel_usage_panel.tab = CelUsageTab::ByCel;
+                ui.close_menu();
+            }
+
+            if ui.add(Button::image_and_text(include_image!("../../assets/film.png"), "Find Unused Cels")).clicked() {
+                app.cel_usage_panel.open = true;
+                app.cel_usage_panel.tab = CelUsageTab::Unused;
+                ui.close_menu();
+            }
+
+            if ui.add(Button::image_and_text(include_image!("../../assets/palette.png"), "Palette Editor")).clicked() {
+                app.palette_editor_panel.open = true;
+                ui.close_menu();
+            }
+
+            if ui.add(Button::new("Affine Matrices")).clicked() {
+                app.affine_matrices_panel.open = true;
+                ui.close_menu();
+            }
+
+            if ui.add(Button::image_and_text(include_image!("../../assets/picture_add.png"), "Tile Editor")).clicked() {
+                app.tile_editor_panel.open = true;
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            if ui.add(Button::new("Reset Viewport").shortcut_text(ui.ctx().format_shortcut(&RESET_VIEWPORT))).clicked() {
+                app.viewport.reset_view();
+                ui.close_menu();
+            }
+        });
     });
 }
\ No newline at end of file
@@ -5,4 +5,13 @@ pub mod oams;
 pub mod properties;
 pub mod viewport;
 pub mod spritesheet;
-pub mod menu_bar;
\ No newline at end of file
+pub mod menu_bar;
+pub mod cel_usage;
+pub mod palette_editor;
+pub mod tile_editor;
+pub mod oam_generator;
+pub mod sequence_importer;
+pub mod spritesheet_optimizer;
+pub mod affine_matrices;
+pub mod cel_transform;
+pub mod remap;
@@ -0,0 +1,260 @@
+use std::path::PathBuf;
+
+use egui::{pos2, vec2, Color32, ColorImage, Pos2, Rect, Sense, Stroke, TextureHandle, Ui};
+use rfd::FileDialog;
+
+use crate::{anim_parser::AnimationCel, oam_fitter::{self, IndexedImage, TileAllocator}, panels::{animation_cells, menu_bar}, AppState, Yanimator};
+
+const PREVIEW_ZOOM: f32 = 2.0;
+
+pub struct OamGeneratorPanel {
+    pub open: bool,
+    image_path: String,
+    preview: Option<TextureHandle>,
+    image_size: [usize; 2],
+    palette: usize,
+    region_x: usize,
+    region_y: usize,
+    region_width: usize,
+    region_height: usize,
+    // Where the cel's (0, 0) is, relative to the region
+    origin_x: i32,
+    origin_y: i32,
+    cel_name: String,
+    drag_start: Option<Pos2>,
+    message: Option<String>
+}
+
+impl OamGeneratorPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            image_path: String::new(),
+            preview: None,
+            image_size: [0, 0],
+            palette: 0,
+            region_x: 0,
+            region_y: 0,
+            region_width: 0,
+            region_height: 0,
+            origin_x: 0,
+            origin_y: 0,
+            cel_name: String::new(),
+            drag_start: None,
+            message: None
+        }
+    }
+}
+
+fn select_image(ui: &mut Ui, app: &mut Yanimator) {
+    let file_path: PathBuf = match FileDialog::new()
+    .add_filter("Image", &["png", "bmp", "gif"])
+    .set_directory("/")
+    .set_title("Select an image")
+    .pick_file() {
+        Some(file) => file,
+        None => return
+    };
+
+    let path_str = match file_path.to_str() {
+        Some(path) => path,
+        None => return
+    };
+
+    let image = match image::open(path_str) {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => {
+            app.oam_generator_panel.message = Some(format!("Couldn't load image: {}", err));
+            return;
+        }
+    };
+
+    let size = [image.width() as usize, image.height() as usize];
+    let panel = &mut app.oam_generator_panel;
+
+    panel.preview = Some(ui.ctx().load_texture(
+        "oam_generator_preview",
+        ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice()),
+        egui::TextureOptions::NEAREST
+    ));
+    panel.image_path = String::from(path_str);
+    panel.image_size = size;
+    panel.region_x = 0;
+    panel.region_y = 0;
+    panel.region_width = size[0];
+    panel.region_height = size[1];
+    panel.origin_x = (size[0] / 2) as i32;
+    panel.origin_y = (size[1] / 2) as i32;
+    panel.message = None;
+}
+
+fn generate_cel(ui: &mut Ui, app: &mut Yanimator) {
+    let panel = &app.oam_generator_panel;
+
+    let palette = match app.palette.palettes.get(panel.palette) {
+        Some(palette) => palette,
+        None => {
+            app.oam_generator_panel.message = Some(String::from("Load a palette first"));
+            return;
+        }
+    };
+
+    let image = match IndexedImage::from_file(&panel.image_path, palette) {
+        Ok(image) => image,
+        Err(err) => {
+            app.oam_generator_panel.message = Some(format!("Couldn't load image: {}", err));
+            return;
+        }
+    };
+
+    let region = image.crop(panel.region_x, panel.region_y, panel.region_width, panel.region_height);
+    let origin = (panel.origin_x, panel.origin_y);
+    let palette_id = panel.palette;
+    let name = panel.cel_name.clone();
+
+    let tile_count = app.spritesheet.sprites.len();
+    let mut allocator = TileAllocator::new(&app.spritesheet);
+
+    match oam_fitter::fit_oams(&region, origin, palette_id, &mut app.spritesheet, &mut allocator) {
+        Ok(oams) => {
+            let new_tiles = app.spritesheet.sprites.len() - tile_count;
+
+            app.oam_generator_panel.message = Some(format!("Created {} with {} OAMs, {} tiles were added to the spritesheet", name, oams.len(), new_tiles));
+            app.animation_cels.insert(name.clone(), AnimationCel { name: name.clone(), oams });
+
+            if new_tiles > 0 {
+                menu_bar::load_texture_handles(ui, app);
+            }
+
+            app.state = AppState::CellEditor;
+            app.edit_oam(&name, 0);
+        },
+        Err(message) => {
+            app.oam_generator_panel.message = Some(message);
+        }
+    }
+}
+
+fn ui_preview(ui: &mut Ui, app: &mut Yanimator) {
+    let panel = &mut app.oam_generator_panel;
+
+    let texture = match &panel.preview {
+        Some(texture) => texture,
+        None => return
+    };
+
+    let size = texture.size_vec2() * PREVIEW_ZOOM;
+    let (rect, response) = ui.allocate_exact_size(size, Sense::drag());
+    let painter = ui.painter_at(rect);
+
+    painter.image(texture.id(), rect, Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)), Color32::WHITE);
+
+    let to_image = |pos: Pos2| {
+        let local = (pos - rect.min) / PREVIEW_ZOOM;
+        (local.x.clamp(0.0, panel.image_size[0] as f32) as usize, local.y.clamp(0.0, panel.image_size[1] as f32) as usize)
+    };
+
+    // Drag on the image to pick the region
+    if response.drag_started() {
+        panel.drag_start = response.interact_pointer_pos();
+    }
+
+    if let (Some(start), Some(current)) = (panel.drag_start, response.interact_pointer_pos()) {
+        let (start_x, start_y) = to_image(start);
+        let (current_x, current_y) = to_image(current);
+
+        panel.region_x = start_x.min(current_x);
+        panel.region_y = start_y.min(current_y);
+        panel.region_width = start_x.abs_diff(current_x);
+        panel.region_height = start_y.abs_diff(current_y);
+        panel.origin_x = (panel.region_width / 2) as i32;
+        panel.origin_y = (panel.region_height / 2) as i32;
+    }
+
+    if response.drag_stopped() {
+        panel.drag_start = None;
+    }
+
+    let region_rect = Rect::from_min_size(
+        rect.min + vec2(panel.region_x as f32, panel.region_y as f32) * PREVIEW_ZOOM,
+        vec2(panel.region_width as f32, panel.region_height as f32) * PREVIEW_ZOOM
+    );
+    painter.rect_stroke(region_rect, 0, Stroke::new(1.0, Color32::YELLOW), egui::StrokeKind::Outside);
+
+    let origin = region_rect.min + vec2(panel.origin_x as f32, panel.origin_y as f32) * PREVIEW_ZOOM;
+    painter.hline(origin.x - 6.0..=origin.x + 6.0, origin.y, Stroke::new(1.0, Color32::RED));
+    painter.vline(origin.x, origin.y - 6.0..=origin.y + 6.0, Stroke::new(1.0, Color32::RED));
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    ui.horizontal(|ui| {
+        if ui.button("Select Image...").clicked() {
+            select_image(ui, app);
+        }
+
+        ui.label(&app.oam_generator_panel.image_path);
+    });
+
+    if app.oam_generator_panel.preview.is_none() {
+        if let Some(message) = &app.oam_generator_panel.message {
+            ui.colored_label(ui.visuals().error_fg_color, message);
+        }
+        return;
+    }
+
+    let [image_width, image_height] = app.oam_generator_panel.image_size;
+    let panel = &mut app.oam_generator_panel;
+
+    egui::Grid::new("oam_generator_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Region");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut panel.region_x).range(0..=image_width).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut panel.region_y).range(0..=image_height).prefix("y: "));
+                ui.add(egui::DragValue::new(&mut panel.region_width).range(0..=image_width - panel.region_x).prefix("w: "));
+                ui.add(egui::DragValue::new(&mut panel.region_height).range(0..=image_height - panel.region_y).prefix("h: "));
+            });
+            ui.end_row();
+
+            ui.label("Origin");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut panel.origin_x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut panel.origin_y).prefix("y: "));
+            });
+            ui.end_row();
+
+            ui.label("Palette");
+            let upper_range = app.palette.palettes.len().saturating_sub(1);
+            ui.add(egui::DragValue::new(&mut panel.palette).speed(0.2).range(0..=upper_range));
+            ui.end_row();
+
+            ui.label("Cel Name");
+            ui.text_edit_singleline(&mut panel.cel_name);
+            ui.end_row();
+        });
+
+    egui::ScrollArea::both()
+    .max_height(400.0)
+    .show(ui, |ui| {
+        ui_preview(ui, app);
+    });
+
+    ui.separator();
+
+    let name_error = animation_cells::get_cell_name_error(app, &app.oam_generator_panel.cel_name);
+
+    if let Some(message) = &name_error {
+        ui.colored_label(ui.visuals().error_fg_color, message);
+    }
+
+    let can_generate = name_error.is_none() && !app.oam_generator_panel.cel_name.is_empty() && app.oam_generator_panel.region_width > 0 && app.oam_generator_panel.region_height > 0;
+
+    if ui.add_enabled(can_generate, egui::Button::new("Generate Cel")).clicked() {
+        generate_cel(ui, app);
+    }
+
+    if let Some(message) = &app.oam_generator_panel.message {
+        ui.label(message);
+    }
+}
@@ -1,6 +1,233 @@
-use egui::{include_image, vec2, ImageButton, Ui};
+use egui::{include_image, pos2, vec2, Color32, Id, ImageButton, InputState, Key, Rect, Sense, Stroke, TextureHandle, Ui};
+use itertools::Itertools;
 
-use crate::{anim_parser::{OAMFlip, OAMShape, OAMSize, OAM}, Yanimator};
+use crate::{anim_parser::{AnimationCel, OAMFlip, OAMMode, OAMShape, OAMSize, OAM}, export, panels::properties, Yanimator};
+
+const THUMBNAIL_SIZE: f32 = 32.0;
+
+// The OAM's tiles scaled to fit in a small square, ignoring affine transforms
+fn oam_thumbnail(ui: &mut Ui, textures: &[Vec<TextureHandle>], oam: &OAM) {
+    let (rect, _) = ui.allocate_exact_size(vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE), Sense::hover());
+    let painter = ui.painter_at(rect);
+
+    painter.rect_filled(rect, 2, ui.visuals().extreme_bg_color);
+
+    let texture_sheet = match textures.get(oam.palette) {
+        Some(texture_sheet) => texture_sheet,
+        None => return
+    };
+
+    let (width, height) = oam.get_width_and_height();
+    let tile_size = THUMBNAIL_SIZE / width.max(height) as f32;
+    let offset = (vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE) - vec2(width as f32, height as f32) * tile_size) / 2.0;
+
+    let uv = match oam.flip {
+        OAMFlip::None => Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+        OAMFlip::Horizontal => Rect::from_min_max(pos2(1.0, 0.0), pos2(0.0, 1.0)),
+        OAMFlip::Vertical => Rect::from_min_max(pos2(0.0, 1.0), pos2(1.0, 0.0)),
+        OAMFlip::Both => Rect::from_min_max(pos2(1.0, 1.0), pos2(0.0, 0.0))
+    };
+
+    for (y, row) in oam.get_sprite_indexes().iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            let texture = match texture_sheet.get(tile) {
+                Some(texture) => texture,
+                None => continue
+            };
+
+            let tile_rect = Rect::from_min_size(
+                rect.min + offset + vec2(x as f32, y as f32) * tile_size,
+                vec2(tile_size, tile_size)
+            );
+
+            painter.image(texture.id(), tile_rect, uv, Color32::WHITE);
+        }
+    }
+}
+
+// Moves an OAM in the list, keeping the same OAM selected
+fn move_oam(app: &mut Yanimator, from: usize, to: usize) {
+    let cell = match app.animation_cels.get_mut(&app.editing_cell) {
+        Some(cell) => cell,
+        None => return,
+    };
+
+    if from >= cell.oams.len() {return;}
+    let to = to.min(cell.oams.len() - 1);
+    if from == to {return;}
+
+    let oam = cell.oams.remove(from);
+    cell.oams.insert(to, oam);
+
+    if app.editing_oam == from {
+        app.editing_oam = to;
+    } else if from < app.editing_oam && to >= app.editing_oam {
+        app.editing_oam -= 1;
+    } else if from > app.editing_oam && to <= app.editing_oam {
+        app.editing_oam += 1;
+    }
+}
+
+// Plain clicks select a single OAM, holding ctrl or shift adds to or removes from the selection
+fn select_oam(app: &mut Yanimator, index: usize, toggle: bool) {
+    let cell = match app.animation_cels.get_mut(&app.editing_cell) {
+        Some(cell) => cell,
+        None => return,
+    };
+
+    if !toggle {
+        for oam in &mut cell.oams {
+            oam.selected = false;
+        }
+    }
+
+    let oam = match cell.oams.get_mut(index) {
+        Some(oam) => oam,
+        None => return,
+    };
+
+    oam.selected = !toggle || !oam.selected;
+
+    if oam.selected {
+        app.editing_oam = index;
+    } else if app.editing_oam == index {
+        // The OAM being edited is always selected, so hand that over to another one
+        if let Some(other) = cell.oams.iter().position(|oam| oam.selected) {
+            app.editing_oam = other;
+        }
+    }
+}
+
+fn get_selected_oams(app: &Yanimator) -> Vec<OAM> {
+    match app.animation_cels.get(&app.editing_cell) {
+        Some(cell) => cell.oams.iter().filter(|oam| oam.selected).cloned().collect(),
+        None => Vec::new()
+    }
+}
+
+// Adds OAMs to the end of the cel being edited and selects them
+pub fn add_oams(app: &mut Yanimator, oams: Vec<OAM>) {
+    let cell = match app.animation_cels.get_mut(&app.editing_cell) {
+        Some(cell) => cell,
+        None => return,
+    };
+
+    if oams.is_empty() {return;}
+
+    for oam in &mut cell.oams {
+        oam.selected = false;
+    }
+
+    app.editing_oam = cell.oams.len();
+
+    cell.oams.extend(oams.into_iter().map(|oam| OAM { selected: true, locked: false, hidden: false, ..oam }));
+}
+
+// Locked OAMs are left alone
+fn remove_selected_oams(app: &mut Yanimator) {
+    let cell = match app.animation_cels.get_mut(&app.editing_cell) {
+        Some(cell) => cell,
+        None => return,
+    };
+
+    cell.oams.retain(|oam| !oam.selected || oam.locked);
+    app.editing_oam = cell.oams.iter().position(|oam| oam.selected).unwrap_or(0);
+}
+
+// Copied OAMs go to the system clipboard as the same three hex words the decomp uses,
+// so they can be pasted into C source and OAMs can be pasted from it
+pub fn copy_oams(app: &mut Yanimator) {
+    let copied_oams = get_selected_oams(app);
+    if copied_oams.is_empty() {return;}
+
+    let text = copied_oams.iter().map(|oam| {
+        let [word1, word2, word3] = export::get_oam_words(oam);
+        format!("0x{:04x}, 0x{:04x}, 0x{:04x},{}", word1, word2, word3, export::get_label_comment(oam))
+    }).join("\n");
+
+    app.clipboard_text = Some(text);
+    app.oam_clipboard = copied_oams;
+}
+
+pub fn cut_oams(app: &mut Yanimator) {
+    copy_oams(app);
+    remove_selected_oams(app);
+}
+
+// Pastes at the same position they were copied from, or centered on the mouse
+pub fn paste_oams(app: &mut Yanimator, at_mouse: bool) {
+    let mut pasted = AnimationCel { name: String::new(), oams: app.oam_clipboard.clone() };
+
+    if let (true, Some((mouse_x, mouse_y)), Some((left, top, right, bottom))) = (at_mouse, app.viewport.cel_mouse_pos, pasted.get_bounds()) {
+        // Stays where it was copied from if it doesn't fit at the mouse
+        let _ = pasted.translate(mouse_x - (left + right) / 2, mouse_y - (top + bottom) / 2);
+    }
+
+    add_oams(app, pasted.oams);
+}
+
+pub fn duplicate_oams(app: &mut Yanimator) {
+    let duplicated = get_selected_oams(app);
+    add_oams(app, duplicated);
+}
+
+fn read_clipboard_text(text: &str) -> Option<Vec<OAM>> {
+    let cel = AnimationCel::from_c(text, "")?;
+
+    if cel.oams.is_empty() {
+        None
+    } else {
+        Some(cel.oams)
+    }
+}
+
+pub fn input(input: &InputState, app: &mut Yanimator) {
+    if app.keyboard_captured {return;}
+
+    for event in &input.events {
+        match event {
+            egui::Event::Copy => copy_oams(app),
+            egui::Event::Cut => cut_oams(app),
+            // Text that isn't OAM words, like copied keyframes, doesn't paste anything
+            egui::Event::Paste(text) => {
+                if let Some(copied_oams) = read_clipboard_text(text) {
+                    app.oam_clipboard = copied_oams;
+                    paste_oams(app, input.modifiers.shift);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    if input.modifiers.command && input.key_pressed(Key::D) {
+        duplicate_oams(app);
+    }
+}
+
+// The first OAM in the list is drawn in front of the others
+fn ui_order_buttons(ui: &mut Ui, app: &mut Yanimator) {
+    let oam_count = app.animation_cels.get(&app.editing_cell).map(|cell| cell.oams.len()).unwrap_or(0);
+    let selected = app.editing_oam;
+    let has_selection = selected < oam_count;
+
+    ui.horizontal(|ui| {
+        if ui.add_enabled(has_selection && selected > 0, egui::Button::new("To Front")).clicked() {
+            move_oam(app, selected, 0);
+        }
+
+        if ui.add_enabled(has_selection && selected > 0, egui::Button::new("Up")).clicked() {
+            move_oam(app, selected, selected - 1);
+        }
+
+        if ui.add_enabled(has_selection && selected + 1 < oam_count, egui::Button::new("Down")).clicked() {
+            move_oam(app, selected, selected + 1);
+        }
+
+        if ui.add_enabled(has_selection && selected + 1 < oam_count, egui::Button::new("To Back")).clicked() {
+            move_oam(app, selected, oam_count - 1);
+        }
+    });
+}
 
 pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
     let cell = match app.animation_cels.get_mut(&app.editing_cell) {
@@ -21,11 +248,30 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
                     y: 0,
                     palette: 0,
                     tile: 0,
+                    affine: false,
+                    double_size: false,
+                    affine_matrix: 0,
+                    mode: OAMMode::Normal,
+                    mosaic: false,
+                    priority: 0,
+                    hidden: false,
+                    locked: false,
+                    label: String::new(),
                     selected: false,
                 });
             }
         });
     });
+
+    ui_order_buttons(ui, app);
+
+    let cell = match app.animation_cels.get_mut(&app.editing_cell) {
+        Some(cell) => cell,
+        None => return,
+    };
+
+    let mut moved_oam: Option<(usize, usize)> = None;
+    let mut clicked_oam: Option<usize> = None;
     
     egui::ScrollArea::vertical()
     .show(ui, |ui| {
@@ -34,22 +280,70 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
         .striped(true)
         .spacing([40.0, 4.0])
         .show(ui, |ui| {
-            let mut i = 0;
-            
-            for _oam in &cell.oams {
-                if ui.button(format!("OAM_{}", i)).clicked() {
-                    app.editing_oam = i;
+            for (i, oam) in cell.oams.iter_mut().enumerate() {
+                let row = ui.horizontal(|ui| {
+                    if ui.selectable_label(!oam.hidden, "👁").on_hover_text("Visible").clicked() {
+                        oam.hidden = !oam.hidden;
+                    }
+
+                    if ui.selectable_label(oam.locked, "🔒").on_hover_text("Locked").clicked() {
+                        oam.locked = !oam.locked;
+                    }
+
+                    oam_thumbnail(ui, &app.textures, oam);
+
+                    ui.vertical(|ui| {
+                        let name = if oam.label.is_empty() {
+                            format!("OAM_{}", i)
+                        } else {
+                            format!("OAM_{}: {}", i, oam.label)
+                        };
+
+                        // Drag the name to reorder
+                        ui.dnd_drag_source(Id::new(("oam_drag", i)), i, |ui| {
+                            if ui.selectable_label(oam.selected || app.editing_oam == i, name).clicked() {
+                                clicked_oam = Some(i);
+                            }
+                        });
+
+                        let mut summary = format!(
+                            "{} at ({}, {}), tile {}",
+                            properties::get_size_string_with_shape(&oam.size, &oam.shape), oam.x, oam.y, oam.tile
+                        );
+
+                        if oam.priority > 0 {
+                            summary.push_str(&format!(", P{}", oam.priority));
+                        }
+
+                        ui.weak(summary);
+                    });
+                }).response;
+
+                if let Some(from) = row.dnd_release_payload::<usize>() {
+                    moved_oam = Some((*from, i));
                 }
-                
-                i += 1;
+
+                if row.dnd_hover_payload::<usize>().is_some() {
+                    ui.painter().hline(row.rect.x_range(), row.rect.top(), Stroke::new(2.0, ui.visuals().selection.stroke.color));
+                }
+
                 ui.end_row();
             }
         });
     });
+
+    if let Some((from, to)) = moved_oam {
+        move_oam(app, from, to);
+    }
+
+    if let Some(i) = clicked_oam {
+        let toggle = ui.input(|input| input.modifiers.command || input.modifiers.shift);
+        select_oam(app, i, toggle);
+    }
     
     let rect = egui::Rect::from_min_size(
         ui.cursor().min,
         egui::vec2(ui.available_width().max(1.0), ui.available_height())
     );
     ui.allocate_rect(rect, egui::Sense::hover());
-}
\ No newline at end of file
+}
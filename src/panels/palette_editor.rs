@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use egui::{vec2, Color32, Sense, Stroke, Ui};
+use rfd::FileDialog;
+
+use crate::{palette_parser::RGB, panels::menu_bar, Yanimator};
+
+const SWATCH_SIZE: f32 = 18.0;
+
+pub struct PaletteEditorPanel {
+    pub open: bool,
+    // (bank, color)
+    selected_color: (usize, usize),
+    save_error: Option<String>
+}
+
+impl PaletteEditorPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            selected_color: (0, 1),
+            save_error: None
+        }
+    }
+}
+
+fn save_palette(app: &mut Yanimator, gbapal: bool) {
+    let (filter_name, extension) = if gbapal { ("GBA palette", "gbapal") } else { ("Palette", "pal") };
+
+    let file_path: PathBuf = match FileDialog::new()
+    .add_filter(filter_name, &[extension])
+    .set_directory("/")
+    .set_title("Select save location")
+    .save_file() {
+        Some(file) => file,
+        None => return
+    };
+
+    let path_str = match file_path.to_str() {
+        Some(path) => path,
+        None => return
+    };
+
+    let result = if gbapal { app.palette.to_gbapal(path_str) } else { app.palette.to_pal(path_str) };
+
+    app.palette_editor_panel.save_error = result.err().map(|err| format!("Couldn't save palette: {}", err));
+}
+
+fn color_swatch(ui: &mut Ui, color: &RGB, selected: bool) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(vec2(SWATCH_SIZE, SWATCH_SIZE), Sense::click());
+
+    ui.painter().rect_filled(rect, 0, Color32::from_rgb(color.r, color.g, color.b));
+
+    let stroke = if selected {
+        Stroke::new(2.0, ui.visuals().selection.stroke.color)
+    } else if response.hovered() {
+        ui.visuals().widgets.hovered.fg_stroke
+    } else {
+        Stroke::new(1.0, Color32::from_gray(60))
+    };
+    ui.painter().rect_stroke(rect, 0, stroke, egui::StrokeKind::Inside);
+
+    response
+}
+
+fn ui_banks(ui: &mut Ui, app: &mut Yanimator) {
+    egui::Grid::new("palette_banks_grid")
+        .spacing(vec2(2.0, 2.0))
+        .show(ui, |ui| {
+            for (bank, colors) in app.palette.palettes.iter().enumerate() {
+                ui.label(format!("{:X}", bank));
+
+                for (i, color) in colors.iter().enumerate() {
+                    let selected = app.palette_editor_panel.selected_color == (bank, i);
+
+                    if color_swatch(ui, color, selected)
+                        .on_hover_text(format!("Bank {}, color {}: 0x{:04X}", bank, i, color.to_bgr555()))
+                        .clicked() {
+                        app.palette_editor_panel.selected_color = (bank, i);
+                    }
+                }
+
+                ui.end_row();
+            }
+        });
+}
+
+// Returns true if the color was changed
+fn ui_color_editor(ui: &mut Ui, color: &mut RGB) -> bool {
+    let mut changed = false;
+    let mut bgr555 = color.to_bgr555();
+
+    ui.horizontal(|ui| {
+        let mut srgb = [color.r, color.g, color.b];
+
+        if ui.color_edit_button_srgb(&mut srgb).changed() {
+            // Snap to what the GBA can actually display
+            bgr555 = RGB { r: srgb[0], g: srgb[1], b: srgb[2] }.to_bgr555();
+            changed = true;
+        }
+
+        let mut channels = [bgr555 & 0x1F, (bgr555 >> 5) & 0x1F, (bgr555 >> 10) & 0x1F];
+
+        for (channel, name) in channels.iter_mut().zip(["R: ", "G: ", "B: "]) {
+            if ui.add(egui::DragValue::new(channel).speed(0.2).range(0..=31).prefix(name)).changed() {
+                changed = true;
+            }
+        }
+
+        if changed {
+            bgr555 = channels[0] | (channels[1] << 5) | (channels[2] << 10);
+        }
+
+        ui.label(format!("0x{:04X}", bgr555));
+    });
+
+    if changed {
+        *color = RGB::from_bgr555(bgr555);
+    }
+
+    changed
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    if app.palette.palettes.is_empty() {
+        ui.label("Load a palette first");
+        return;
+    }
+
+    ui_banks(ui, app);
+
+    ui.separator();
+
+    let (bank, i) = app.palette_editor_panel.selected_color;
+    if let Some(color) = app.palette.palettes.get_mut(bank).and_then(|colors| colors.get_mut(i)) {
+        ui.label(format!("Bank {}, color {}", bank, i));
+
+        if i == 0 {
+            ui.label("Color 0 is always transparent on sprites");
+        }
+
+        if ui_color_editor(ui, color) {
+            menu_bar::update_palette_textures(app, bank);
+        }
+    }
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.button("Save .pal").clicked() {
+            save_palette(app, false);
+        }
+
+        if ui.button("Save .gbapal").clicked() {
+            save_palette(app, true);
+        }
+    });
+
+    if let Some(message) = &app.palette_editor_panel.save_error {
+        ui.colored_label(ui.visuals().error_fg_color, message);
+    }
+}
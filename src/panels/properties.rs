@@ -1,8 +1,8 @@
 use egui::{InputState, Key, Ui};
 
-use crate::{anim_parser::{OAMFlip, OAMShape, OAMSize}, Yanimator};
+use crate::{anim_parser::{OAMFlip, OAMMode, OAMShape, OAMSize}, panels::affine_matrices::MAX_AFFINE_MATRICES, Yanimator};
 
-fn get_size_string_with_shape<'a>(size: &'a OAMSize, shape: &'a OAMShape) -> &'a str {
+pub fn get_size_string_with_shape<'a>(size: &'a OAMSize, shape: &'a OAMShape) -> &'a str {
     match shape {
         OAMShape::Square => {
             match size {
@@ -56,74 +56,138 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
     //let sprites_len = app.spritesheet.sprites.len();
     //let palette_len = app.palette.palettes.len();
 
-    egui::Grid::new("animation_cells")
-        .num_columns(2)
-        .striped(true)
-        .spacing([40.0, 4.0])
-        .show(ui, |ui| {
-        
-            ui.label("X");
-            ui.add(egui::DragValue::new(&mut oam.x).speed(0.2));
-            ui.end_row();
-
-            ui.label("Y");
-            ui.add(egui::DragValue::new(&mut oam.y).speed(0.2));
-            ui.end_row();
-        
-            ui.label("Tile ID");
-            ui.add(egui::DragValue::new(&mut oam.tile).speed(0.2).range(0..=app.spritesheet.sprites.len()));
-            ui.end_row();
-            
-            ui.label("Palette ID");
-            
-            // the world would be a perfect place
-            // if these drag value ranged didnt have to be inclusive
-            
-            let mut upper_range = app.palette.palettes.len();
-            
-            if upper_range > 0 {
-                upper_range -= 1;
-            }
+    let locked = oam.locked;
+
+    if locked {
+        ui.label("This OAM is locked, unlock it in the OAM list to edit it");
+    }
+
+    ui.add_enabled_ui(!locked, |ui| {
+        egui::Grid::new("animation_cells")
+            .num_columns(2)
+            .striped(true)
+            .spacing([40.0, 4.0])
+            .show(ui, |ui| {
             
-            ui.add(egui::DragValue::new(&mut oam.palette).speed(0.2).range(0..=upper_range));
-            ui.end_row();
+                ui.label("Label");
+                ui.add(egui::TextEdit::singleline(&mut oam.label).hint_text("Exported as a comment"));
+                ui.end_row();
+
+                ui.label("X");
+                ui.add(egui::DragValue::new(&mut oam.x).speed(0.2));
+                ui.end_row();
+
+                ui.label("Y");
+                ui.add(egui::DragValue::new(&mut oam.y).speed(0.2));
+                ui.end_row();
             
-            ui.label("Shape");
-            egui::ComboBox::from_id_salt("shape_dropdown")
-                .selected_text(format!("{:?}", &mut oam.shape))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut oam.shape, OAMShape::Square, "Square");
-                    ui.selectable_value(&mut oam.shape, OAMShape::Horizontal, "Horizontal");
-                    ui.selectable_value(&mut oam.shape, OAMShape::Vertical, "Vertical");
-                });
-            ui.end_row();
-
-            ui.label("Size");
-            egui::ComboBox::from_id_salt("size_dropdown")
-                .selected_text(format!("{}", get_size_string_with_shape(&oam.size, &oam.shape)))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut oam.size, OAMSize::Size0, get_size_string_with_shape(&OAMSize::Size0, &oam.shape));
-                    ui.selectable_value(&mut oam.size, OAMSize::Size1, get_size_string_with_shape(&OAMSize::Size1, &oam.shape));
-                    ui.selectable_value(&mut oam.size, OAMSize::Size2, get_size_string_with_shape(&OAMSize::Size2, &oam.shape));
-                    ui.selectable_value(&mut oam.size, OAMSize::Size3, get_size_string_with_shape(&OAMSize::Size3, &oam.shape));
+                ui.label("Tile ID");
+                ui.add(egui::DragValue::new(&mut oam.tile).speed(0.2).range(0..=app.spritesheet.sprites.len()));
+                ui.end_row();
+                
+                ui.label("Palette ID");
+                
+                // the world would be a perfect place
+                // if these drag value ranged didnt have to be inclusive
+                
+                let mut upper_range = app.palette.palettes.len();
+                
+                if upper_range > 0 {
+                    upper_range -= 1;
+                }
+                
+                ui.add(egui::DragValue::new(&mut oam.palette).speed(0.2).range(0..=upper_range));
+                ui.end_row();
+
+                // Lower numbers are drawn in front, over backgrounds of the same priority
+                ui.label("Priority");
+                ui.add(egui::DragValue::new(&mut oam.priority).speed(0.1).range(0..=3));
+                ui.end_row();
+                
+                ui.label("Shape");
+                egui::ComboBox::from_id_salt("shape_dropdown")
+                    .selected_text(format!("{:?}", &mut oam.shape))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut oam.shape, OAMShape::Square, "Square");
+                        ui.selectable_value(&mut oam.shape, OAMShape::Horizontal, "Horizontal");
+                        ui.selectable_value(&mut oam.shape, OAMShape::Vertical, "Vertical");
+                    });
+                ui.end_row();
+
+                ui.label("Size");
+                egui::ComboBox::from_id_salt("size_dropdown")
+                    .selected_text(format!("{}", get_size_string_with_shape(&oam.size, &oam.shape)))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut oam.size, OAMSize::Size0, get_size_string_with_shape(&OAMSize::Size0, &oam.shape));
+                        ui.selectable_value(&mut oam.size, OAMSize::Size1, get_size_string_with_shape(&OAMSize::Size1, &oam.shape));
+                        ui.selectable_value(&mut oam.size, OAMSize::Size2, get_size_string_with_shape(&OAMSize::Size2, &oam.shape));
+                        ui.selectable_value(&mut oam.size, OAMSize::Size3, get_size_string_with_shape(&OAMSize::Size3, &oam.shape));
+                    });
+                ui.end_row();
+
+                ui.label("Flip");
+                // Affine OAMs use their matrix for flipping
+                ui.add_enabled_ui(!oam.affine, |ui| {
+                    egui::ComboBox::from_id_salt("flip_dropdown")
+                        .selected_text(format!("{:?}", &mut oam.flip))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut oam.flip, OAMFlip::None, "None");
+                            ui.selectable_value(&mut oam.flip, OAMFlip::Horizontal, "Horizontal");
+                            ui.selectable_value(&mut oam.flip, OAMFlip::Vertical, "Vertical");
+                            ui.selectable_value(&mut oam.flip, OAMFlip::Both, "Both");
+                        });
                 });
-            ui.end_row();
-
-            ui.label("Flip");
-            egui::ComboBox::from_id_salt("flip_dropdown")
-                .selected_text(format!("{:?}", &mut oam.flip))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut oam.flip, OAMFlip::None, "None");
-                    ui.selectable_value(&mut oam.flip, OAMFlip::Horizontal, "Horizontal");
-                    ui.selectable_value(&mut oam.flip, OAMFlip::Vertical, "Vertical");
-                    ui.selectable_value(&mut oam.flip, OAMFlip::Both, "Both");
-                });
-            ui.end_row();
-        });
+                ui.end_row();
+
+                ui.label("Mode");
+                egui::ComboBox::from_id_salt("mode_dropdown")
+                    .selected_text(match oam.mode {
+                        OAMMode::Normal => "Normal",
+                        OAMMode::SemiTransparent => "Semi-Transparent",
+                        OAMMode::Window => "OBJ Window"
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut oam.mode, OAMMode::Normal, "Normal");
+                        ui.selectable_value(&mut oam.mode, OAMMode::SemiTransparent, "Semi-Transparent");
+                        ui.selectable_value(&mut oam.mode, OAMMode::Window, "OBJ Window");
+                    });
+                ui.end_row();
+
+                ui.label("Mosaic");
+                ui.checkbox(&mut oam.mosaic, "");
+                ui.end_row();
+
+                ui.label("Affine");
+                ui.checkbox(&mut oam.affine, "");
+                ui.end_row();
+
+                if oam.affine {
+                    ui.label("Double Size");
+                    ui.checkbox(&mut oam.double_size, "");
+                    ui.end_row();
+
+                    ui.label("Matrix ID");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut oam.affine_matrix).speed(0.2).range(0..=MAX_AFFINE_MATRICES - 1));
+
+                        if ui.button("Edit").clicked() {
+                            app.affine_matrices_panel.open = true;
+                        }
+                    });
+                    ui.end_row();
+
+                    if oam.affine_matrix >= app.affine_matrices.len() {
+                        ui.label("");
+                        ui.label(format!("Matrix {} doesn't exist yet", oam.affine_matrix));
+                        ui.end_row();
+                    }
+                }
+            });
+    });
 
     ui.separator();
 
-    if ui.button("Delete OAM").clicked() {
+    if ui.add_enabled(!locked, egui::Button::new("Delete OAM")).clicked() {
         remove_oam(app);
     }
     
@@ -136,29 +200,34 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
 }
 
 pub fn input(input: &InputState, app: &mut Yanimator) {
+    if app.keyboard_captured {return;}
+
     let cell  = match app.animation_cels.get_mut(&app.editing_cell) {
         Some(cell) => cell,
         None => return,
     };
-    
-    let oam = match cell.oams.get_mut(app.editing_oam) {
-        Some(oam) => oam,
-        None => return,
-    };
+
+    let (mut x, mut y) = (0, 0);
 
     if input.key_pressed(Key::ArrowUp) {
-        oam.y -= 1;
+        y -= 1;
     }
 
     if input.key_pressed(Key::ArrowDown) {
-        oam.y += 1;
+        y += 1;
     }
 
     if input.key_pressed(Key::ArrowLeft) {
-        oam.x -= 1;
+        x -= 1;
     }
 
     if input.key_pressed(Key::ArrowRight) {
-        oam.x += 1;
+        x += 1;
     }
-}
\ No newline at end of file
+
+    // Every selected OAM moves together
+    for oam in cell.oams.iter_mut().filter(|oam| oam.selected && !oam.locked) {
+        oam.x = oam.x.wrapping_add(x);
+        oam.y = oam.y.wrapping_add(y);
+    }
+}
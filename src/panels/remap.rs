@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+
+use egui::Ui;
+use itertools::Itertools;
+
+use crate::{sprite_parser::MAX_TILE, Yanimator};
+
+const MAX_PALETTE: usize = 15;
+
+#[derive(PartialEq, Clone, Copy)]
+enum RemapScope {
+    AllCels,
+    SelectedCels,
+    // Every cel used by the animation
+    Animation(usize)
+}
+
+pub struct RemapPanel {
+    pub open: bool,
+    scope: RemapScope,
+    selected_cels: HashSet<String>,
+    cel_filter: String,
+    remap_tiles: bool,
+    // Inclusive range of tiles that get moved
+    tile_start: usize,
+    tile_end: usize,
+    tile_offset: i32,
+    remap_palettes: bool,
+    palette_from: usize,
+    palette_to: usize,
+    message: Option<String>
+}
+
+impl RemapPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            scope: RemapScope::AllCels,
+            selected_cels: HashSet::new(),
+            cel_filter: String::new(),
+            remap_tiles: true,
+            tile_start: 0,
+            tile_end: MAX_TILE,
+            tile_offset: 0,
+            remap_palettes: false,
+            palette_from: 0,
+            palette_to: 0,
+            message: None
+        }
+    }
+}
+
+// What would happen to a single OAM, as (old, new) values
+struct RemapChange {
+    cel: String,
+    oam: usize,
+    tile: Option<(usize, i64)>,
+    palette: Option<(usize, usize)>,
+    // Only some of the OAM's tiles are in the range, so moving it would take the others along
+    straddles: bool,
+    // Every tile of the OAM would still be one an OAM can point at
+    tiles_valid: bool
+}
+
+fn get_scope_cels(app: &Yanimator) -> Vec<String> {
+    let panel = &app.remap_panel;
+
+    let cels: Vec<String> = match panel.scope {
+        RemapScope::AllCels => app.animation_cels.keys().cloned().collect(),
+        RemapScope::SelectedCels => panel.selected_cels.iter().cloned().collect(),
+        RemapScope::Animation(animation_id) => match app.animations.get(animation_id) {
+            Some(animation) => animation.get_used_cels().into_iter().cloned().collect(),
+            None => Vec::new()
+        }
+    };
+
+    cels.into_iter().filter(|name| app.animation_cels.contains_key(name)).sorted().collect()
+}
+
+// An OAM is moved when every tile it uses is in the range. OAMs only partly in it are
+// still listed, so the remap can be refused instead of leaving some of their tiles behind
+fn get_changes(app: &Yanimator) -> Vec<RemapChange> {
+    let panel = &app.remap_panel;
+    let mut changes = Vec::new();
+
+    for name in get_scope_cels(app) {
+        let cel = match app.animation_cels.get(&name) {
+            Some(cel) => cel,
+            None => continue
+        };
+
+        for (i, oam) in cel.oams.iter().enumerate() {
+            let tiles = oam.get_sprite_indexes_one_dimensional();
+            let tiles_in_range = tiles.iter().filter(|tile| (panel.tile_start..=panel.tile_end).contains(tile)).count();
+            let moved = panel.remap_tiles && panel.tile_offset != 0 && tiles_in_range > 0;
+
+            let tile = if moved {
+                Some((oam.tile, oam.tile as i64 + panel.tile_offset as i64))
+            } else {
+                None
+            };
+
+            let straddles = moved && tiles_in_range < tiles.len();
+            let tiles_valid = !moved || tiles.iter().all(|&tile| is_tile_valid(tile as i64 + panel.tile_offset as i64));
+
+            let palette = if panel.remap_palettes && panel.palette_from != panel.palette_to && oam.palette == panel.palette_from {
+                Some((oam.palette, panel.palette_to))
+            } else {
+                None
+            };
+
+            if tile.is_some() || palette.is_some() {
+                changes.push(RemapChange { cel: name.clone(), oam: i, tile, palette, straddles, tiles_valid });
+            }
+        }
+    }
+
+    changes
+}
+
+fn is_tile_valid(tile: i64) -> bool {
+    (0..=MAX_TILE as i64).contains(&tile)
+}
+
+fn get_change_error(panel: &RemapPanel, changes: &[RemapChange]) -> Option<String> {
+    if panel.remap_tiles && panel.tile_start > panel.tile_end {
+        return Some(String::from("The first tile of the range is after the last one"));
+    }
+
+    let straddling = changes.iter().filter(|change| change.straddles).count();
+
+    if straddling > 0 {
+        return Some(format!("{} OAMs use tiles both inside and outside of the range", straddling));
+    }
+
+    let out_of_range = changes.iter().filter(|change| !change.tiles_valid).count();
+
+    if out_of_range > 0 {
+        return Some(format!("{} OAMs would end up outside of tiles 0x000 to 0x{:03x}", out_of_range, MAX_TILE));
+    }
+
+    None
+}
+
+fn apply_changes(app: &mut Yanimator) {
+    let changes = get_changes(app);
+
+    if let Some(error) = get_change_error(&app.remap_panel, &changes) {
+        app.remap_panel.message = Some(error);
+        return;
+    }
+
+    for change in &changes {
+        let oam = match app.animation_cels.get_mut(&change.cel).and_then(|cel| cel.oams.get_mut(change.oam)) {
+            Some(oam) => oam,
+            None => continue
+        };
+
+        if let Some((_, tile)) = change.tile {
+            oam.tile = tile as usize;
+        }
+
+        if let Some((_, palette)) = change.palette {
+            oam.palette = palette;
+        }
+    }
+
+    let cel_count = changes.iter().map(|change| &change.cel).unique().count();
+    app.remap_panel.message = Some(format!("Changed {} OAMs in {} cels", changes.len(), cel_count));
+}
+
+fn ui_scope(ui: &mut Ui, app: &mut Yanimator) {
+    let panel = &mut app.remap_panel;
+
+    ui.horizontal(|ui| {
+        ui.label("Scope");
+        ui.radio_value(&mut panel.scope, RemapScope::AllCels, "All Cels");
+        ui.radio_value(&mut panel.scope, RemapScope::SelectedCels, "Selected Cels");
+
+        let animation_id = match panel.scope {
+            RemapScope::Animation(animation_id) => animation_id,
+            _ => app.animation_id
+        };
+
+        if ui.radio(matches!(panel.scope, RemapScope::Animation(_)), "Animation").clicked() {
+            panel.scope = RemapScope::Animation(animation_id);
+        }
+
+        if let RemapScope::Animation(animation_id) = &mut panel.scope {
+            egui::ComboBox::from_id_salt("remap_animation")
+                .selected_text(app.animations.get(*animation_id).map(|animation| animation.name.as_str()).unwrap_or(""))
+                .show_ui(ui, |ui| {
+                    for (i, animation) in app.animations.iter().enumerate() {
+                        ui.selectable_value(animation_id, i, &animation.name);
+                    }
+                });
+        }
+    });
+
+    if panel.scope != RemapScope::SelectedCels {return;}
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut panel.cel_filter).hint_text("Filter"));
+
+        if ui.button("Select All").clicked() {
+            let filter = panel.cel_filter.to_lowercase();
+            panel.selected_cels.extend(app.animation_cels.keys().filter(|name| name.to_lowercase().contains(&filter)).cloned());
+        }
+
+        if ui.button("Select None").clicked() {
+            panel.selected_cels.clear();
+        }
+    });
+
+    egui::ScrollArea::vertical()
+    .id_salt("remap_cels")
+    .max_height(120.0)
+    .show(ui, |ui| {
+        let filter = panel.cel_filter.to_lowercase();
+
+        for name in app.animation_cels.keys().filter(|name| name.to_lowercase().contains(&filter)).sorted() {
+            let mut selected = panel.selected_cels.contains(name);
+
+            if ui.checkbox(&mut selected, name).changed() {
+                if selected {
+                    panel.selected_cels.insert(name.clone());
+                } else {
+                    panel.selected_cels.remove(name);
+                }
+            }
+        }
+    });
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    ui_scope(ui, app);
+
+    ui.separator();
+
+    let panel = &mut app.remap_panel;
+
+    egui::Grid::new("remap_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.checkbox(&mut panel.remap_tiles, "Tiles");
+            ui.add_enabled_ui(panel.remap_tiles, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut panel.tile_start).hexadecimal(3, false, false).prefix("0x").range(0..=MAX_TILE));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(&mut panel.tile_end).hexadecimal(3, false, false).prefix("0x").range(0..=MAX_TILE));
+                    ui.label("move by");
+                    ui.add(egui::DragValue::new(&mut panel.tile_offset).range(-(MAX_TILE as i32)..=MAX_TILE as i32));
+                });
+            });
+            ui.end_row();
+
+            ui.checkbox(&mut panel.remap_palettes, "Palettes");
+            ui.add_enabled_ui(panel.remap_palettes, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut panel.palette_from).speed(0.2).range(0..=MAX_PALETTE));
+                    ui.label("becomes");
+                    ui.add(egui::DragValue::new(&mut panel.palette_to).speed(0.2).range(0..=MAX_PALETTE));
+                });
+            });
+            ui.end_row();
+        });
+
+    ui.separator();
+
+    let changes = get_changes(app);
+    let error = get_change_error(&app.remap_panel, &changes);
+
+    ui.label(format!("{} OAMs will change", changes.len()));
+
+    egui::ScrollArea::vertical()
+    .id_salt("remap_preview")
+    .max_height(200.0)
+    .show(ui, |ui| {
+        for change in &changes {
+            let mut text = format!("{} OAM_{}", change.cel, change.oam);
+
+            if let Some((old, new)) = change.tile {
+                let sign = if new < 0 { "-" } else { "" };
+                text.push_str(&format!(", tile 0x{:03x} -> {}0x{:03x}", old, sign, new.abs()));
+            }
+
+            if let Some((old, new)) = change.palette {
+                text.push_str(&format!(", palette {} -> {}", old, new));
+            }
+
+            if change.straddles {
+                text.push_str(", only partly in the range");
+            }
+
+            if change.straddles || !change.tiles_valid {
+                ui.colored_label(ui.visuals().error_fg_color, text);
+            } else {
+                ui.label(text);
+            }
+        }
+    });
+
+    ui.separator();
+
+    if let Some(error) = &error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    if ui.add_enabled(error.is_none() && !changes.is_empty(), egui::Button::new("Apply")).clicked() {
+        apply_changes(app);
+    }
+
+    if let Some(message) = &app.remap_panel.message {
+        ui.label(message);
+    }
+}
@@ -0,0 +1,298 @@
+use std::path::PathBuf;
+
+use egui::Ui;
+use rfd::FileDialog;
+
+use crate::{anim_parser::{Animation, AnimationCel, AnimationFrame}, import::{self, SequenceFrame}, oam_fitter::{self, IndexedImage, TileAllocator}, panels::{animation_cells, menu_bar, topbar}, sprite_parser::MAX_TILE, AppState, Yanimator};
+
+pub struct SequenceImporterPanel {
+    pub open: bool,
+    folder: String,
+    frames: Vec<SequenceFrame>,
+    default_duration: u8,
+    palette: usize,
+    // Where the cel's (0, 0) is in every frame
+    origin_x: i32,
+    origin_y: i32,
+    animation_name: String,
+    cel_prefix: String,
+    message: Option<String>
+}
+
+impl SequenceImporterPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            folder: String::new(),
+            frames: Vec::new(),
+            default_duration: 4,
+            palette: 0,
+            origin_x: 0,
+            origin_y: 0,
+            animation_name: String::new(),
+            cel_prefix: String::new(),
+            message: None
+        }
+    }
+}
+
+fn select_folder(app: &mut Yanimator) {
+    let folder_path: PathBuf = match FileDialog::new()
+    .set_directory("/")
+    .set_title("Select a folder of frames")
+    .pick_folder() {
+        Some(folder) => folder,
+        None => return
+    };
+
+    let path_str = match folder_path.to_str() {
+        Some(path) => path,
+        None => return
+    };
+
+    let panel = &mut app.sequence_importer_panel;
+    panel.folder = String::from(path_str);
+    panel.message = None;
+
+    match import::load_png_sequence(path_str) {
+        Ok(frames) => {
+            // Center the origin on the first frame
+            if let Some(Ok((width, height))) = frames.first().map(|frame| image::image_dimensions(&frame.path)) {
+                panel.origin_x = (width / 2) as i32;
+                panel.origin_y = (height / 2) as i32;
+            }
+
+            if panel.animation_name.is_empty() {
+                if let Some(folder_name) = folder_path.file_name().and_then(|name| name.to_str()) {
+                    panel.animation_name = folder_name.replace([' ', '-'], "_");
+                }
+            }
+
+            panel.frames = frames;
+        },
+        Err(err) => {
+            panel.frames.clear();
+            panel.message = Some(format!("Couldn't read folder: {}", err));
+        }
+    }
+}
+
+fn get_free_cel_name(app: &Yanimator, prefix: &str, start: usize) -> (String, usize) {
+    let mut i = start;
+
+    while app.animation_cels.contains_key(&format!("{}_{}", prefix, i)) {
+        i += 1;
+    }
+
+    (format!("{}_{}", prefix, i), i + 1)
+}
+
+fn get_cel_prefix(panel: &SequenceImporterPanel) -> &str {
+    if panel.cel_prefix.is_empty() { &panel.animation_name } else { &panel.cel_prefix }
+}
+
+// The first generated name is always free, so this only catches names that aren't valid C
+fn get_cel_prefix_error(app: &Yanimator) -> Option<String> {
+    let (first_name, _) = get_free_cel_name(app, get_cel_prefix(&app.sequence_importer_panel), 0);
+    animation_cells::get_cell_name_error(app, &first_name)
+}
+
+// Removes everything an import added so far
+fn undo_import(app: &mut Yanimator, tile_count: usize, cel_names: &[String]) {
+    app.spritesheet.sprites.truncate(tile_count);
+
+    for name in cel_names {
+        app.animation_cels.remove(name);
+    }
+}
+
+fn import_sequence(ui: &mut Ui, app: &mut Yanimator) {
+    let panel = &app.sequence_importer_panel;
+
+    let palette = match app.palette.palettes.get(panel.palette) {
+        Some(palette) => palette,
+        None => {
+            app.sequence_importer_panel.message = Some(String::from("Load a palette first"));
+            return;
+        }
+    };
+
+    // Every frame is loaded before anything is changed, so a broken file doesn't leave a half imported animation
+    let mut images = Vec::new();
+    for frame in &panel.frames {
+        match IndexedImage::from_file(&frame.path, palette) {
+            Ok(image) => images.push(image),
+            Err(err) => {
+                app.sequence_importer_panel.message = Some(format!("Couldn't load {}: {}", frame.file_name, err));
+                return;
+            }
+        }
+    }
+
+    let durations: Vec<u8> = panel.frames.iter().map(|frame| frame.duration.unwrap_or(panel.default_duration)).collect();
+    let origin = (panel.origin_x, panel.origin_y);
+    let palette_id = panel.palette;
+    let animation_name = panel.animation_name.clone();
+    let cel_prefix = String::from(get_cel_prefix(panel));
+
+    let tile_count = app.spritesheet.sprites.len();
+    let mut allocator = TileAllocator::new(&app.spritesheet);
+
+    // (image, cel name) of every unique frame
+    let mut unique_frames: Vec<(&IndexedImage, String)> = Vec::new();
+    let mut frames: Vec<AnimationFrame> = Vec::new();
+    let mut next_cel_id = 0;
+
+    for (image, duration) in images.iter().zip(durations) {
+        let cel_name = match unique_frames.iter().find(|(unique_image, _)| *unique_image == image) {
+            Some((_, name)) => name.clone(),
+            None => {
+                let oams = match oam_fitter::fit_oams(image, origin, palette_id, &mut app.spritesheet, &mut allocator) {
+                    Ok(oams) => oams,
+                    Err(message) => {
+                        let cel_names: Vec<String> = unique_frames.into_iter().map(|(_, name)| name).collect();
+                        undo_import(app, tile_count, &cel_names);
+
+                        app.sequence_importer_panel.message = Some(message);
+                        return;
+                    }
+                };
+
+                let (name, next_id) = get_free_cel_name(app, &cel_prefix, next_cel_id);
+                next_cel_id = next_id;
+
+                app.animation_cels.insert(name.clone(), AnimationCel { name: name.clone(), oams });
+                unique_frames.push((image, name.clone()));
+                name
+            }
+        };
+
+        // A frame held for a while only needs one keyframe
+        if let Some(last_frame) = frames.last_mut() {
+            if last_frame.cell == cel_name && last_frame.duration as usize + duration as usize <= u8::MAX as usize {
+                last_frame.duration += duration;
+                continue;
+            }
+        }
+
+        frames.push(AnimationFrame {
+            cell: cel_name,
+            duration,
+            id: frames.len()
+        });
+    }
+
+    // fit_oams already stops at the last tile, this is in case that ever changes
+    if app.spritesheet.sprites.len() > MAX_TILE + 1 {
+        let cel_names: Vec<String> = unique_frames.into_iter().map(|(_, name)| name).collect();
+        undo_import(app, tile_count, &cel_names);
+
+        app.sequence_importer_panel.message = Some(format!("The sequence doesn't fit in the spritesheet, OAMs can only use tiles 0x000 to 0x{:03x}", MAX_TILE));
+        return;
+    }
+
+    let total_duration = frames.iter().map(|frame| frame.duration as usize).sum();
+    let frame_count = frames.len();
+
+    app.animations.push(Animation {
+        frames,
+        name: animation_name.clone(),
+        current_frame: 0,
+        duration: total_duration
+    });
+
+    let new_tiles = app.spritesheet.sprites.len() - tile_count;
+    if new_tiles > 0 {
+        menu_bar::load_texture_handles(ui, app);
+    }
+
+    app.sequence_importer_panel.message = Some(format!(
+        "Created {} with {} keyframes and {} cels, {} tiles were added to the spritesheet",
+        animation_name, frame_count, unique_frames.len(), new_tiles
+    ));
+
+    app.state = AppState::AnimationEditor;
+    app.animation_id = app.animations.len() - 1;
+    app.frames = 0;
+    app.timeline.keyframes.clear();
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    ui.horizontal(|ui| {
+        if ui.button("Select Folder...").clicked() {
+            select_folder(app);
+        }
+
+        ui.label(&app.sequence_importer_panel.folder);
+    });
+
+    let panel = &mut app.sequence_importer_panel;
+
+    egui::Grid::new("sequence_importer_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Animation Name");
+            ui.text_edit_singleline(&mut panel.animation_name);
+            ui.end_row();
+
+            ui.label("Cel Prefix");
+            ui.add(egui::TextEdit::singleline(&mut panel.cel_prefix).hint_text(&panel.animation_name));
+            ui.end_row();
+
+            ui.label("Default Duration");
+            ui.add(egui::DragValue::new(&mut panel.default_duration).range(1..=255));
+            ui.end_row();
+
+            ui.label("Origin");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut panel.origin_x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut panel.origin_y).prefix("y: "));
+            });
+            ui.end_row();
+
+            ui.label("Palette");
+            let upper_range = app.palette.palettes.len().saturating_sub(1);
+            ui.add(egui::DragValue::new(&mut panel.palette).speed(0.2).range(0..=upper_range));
+            ui.end_row();
+        });
+
+    ui.separator();
+
+    if panel.frames.is_empty() {
+        ui.label("No frames found");
+    } else {
+        ui.label(format!("{} frames", panel.frames.len()));
+
+        egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            for frame in &panel.frames {
+                let duration = match frame.duration {
+                    Some(duration) => duration.to_string(),
+                    None => format!("{} (default)", panel.default_duration)
+                };
+
+                ui.label(format!("{}: {}", frame.file_name, duration));
+            }
+        });
+    }
+
+    ui.separator();
+
+    let name_error = topbar::get_anim_name_error(app, &app.sequence_importer_panel.animation_name)
+        .or_else(|| get_cel_prefix_error(app));
+
+    if let Some(message) = &name_error {
+        ui.colored_label(ui.visuals().error_fg_color, message);
+    }
+
+    let can_import = name_error.is_none() && !app.sequence_importer_panel.animation_name.is_empty() && !app.sequence_importer_panel.frames.is_empty();
+
+    if ui.add_enabled(can_import, egui::Button::new("Import")).clicked() {
+        import_sequence(ui, app);
+    }
+
+    if let Some(message) = &app.sequence_importer_panel.message {
+        ui.label(message);
+    }
+}
@@ -1,65 +1,426 @@
-use egui::{vec2, Color32, Ui};
+use egui::{pos2, vec2, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Ui};
+use itertools::Itertools;
 
-use crate::Yanimator;
+use crate::{anim_parser::{OAMFlip, OAMMode, OAMShape, OAMSize, OAM}, oam_fitter, panels::{oams, properties}, sprite_parser::SHEET_WIDTH, AppState, Yanimator};
 
 const SPRITE_SIZE: f32 = 20.0;
 
-pub fn ui(ui: &mut Ui, app: &mut Yanimator) { 
-    let cell  = match app.animation_cels.get_mut(&app.editing_cell) {
-        Some(cell) => cell,
-        None => return,
+// A rectangle of tiles dragged out on the sheet
+#[derive(Clone, Copy)]
+struct TileRect {
+    tile: usize,
+    width: usize,
+    height: usize
+}
+
+pub struct SpritesheetPanel {
+    zoom: f32,
+    // Preview the sheet with the palette of the OAM being edited
+    use_oam_palette: bool,
+    show_indices: bool,
+    drag_start: Option<usize>,
+    // A rectangle that isn't an OAM size, waiting to be split
+    pending_split: Option<TileRect>,
+    split_error: Option<String>,
+    // Colors tiles by how many OAMs use them
+    heatmap: bool,
+    // Lists what uses the hovered tile, or the one picked with a right click
+    show_usage: bool,
+    lookup_tile: Option<usize>,
+    hovered_tile: Option<usize>
+}
+
+impl SpritesheetPanel {
+    pub fn init() -> Self {
+        Self {
+            zoom: 1.0,
+            use_oam_palette: true,
+            show_indices: false,
+            drag_start: None,
+            pending_split: None,
+            split_error: None,
+            heatmap: false,
+            show_usage: false,
+            lookup_tile: None,
+            hovered_tile: None
+        }
+    }
+}
+
+fn create_oam(shape: OAMShape, size: OAMSize, tile: usize, x: i8, y: i8, palette: usize) -> OAM {
+    OAM {
+        shape,
+        size,
+        flip: OAMFlip::None,
+        x,
+        y,
+        palette,
+        tile,
+        affine: false,
+        double_size: false,
+        affine_matrix: 0,
+        mode: OAMMode::Normal,
+        mosaic: false,
+        priority: 0,
+        hidden: false,
+        locked: false,
+        label: String::new(),
+        selected: false
+    }
+}
+
+// Where new OAMs go and which palette they use, taken from the OAM being edited
+fn get_new_oam_base(app: &Yanimator) -> (i8, i8, usize) {
+    match app.animation_cels.get(&app.editing_cell).and_then(|cell| cell.oams.get(app.editing_oam)) {
+        Some(oam) => (oam.x, oam.y, oam.palette),
+        None => (0, 0, app.spritesheet_palette)
+    }
+}
+
+// Retargets the OAM being edited to the rectangle, or adds a new OAM if there isn't an
+// unlocked one to change or `create_new` is set. Rectangles that aren't an OAM size wait to be split.
+fn use_tile_rect(app: &mut Yanimator, tile_rect: TileRect, create_new: bool) {
+    let (shape, size) = match oam_fitter::get_shape_for_size(tile_rect.width, tile_rect.height) {
+        Some(shape) => shape,
+        None => {
+            app.spritesheet_panel.pending_split = Some(tile_rect);
+            app.spritesheet_panel.split_error = None;
+            return;
+        }
     };
-    
-    let oam = match cell.oams.get_mut(app.editing_oam) {
-        Some(oam) => oam,
-        None => return,
+
+    app.spritesheet_panel.pending_split = None;
+    app.spritesheet_panel.split_error = None;
+
+    if !create_new {
+        let oam = app.animation_cels.get_mut(&app.editing_cell).and_then(|cell| cell.oams.get_mut(app.editing_oam));
+
+        if let Some(oam) = oam.filter(|oam| !oam.locked) {
+            oam.shape = shape;
+            oam.size = size;
+            oam.tile = tile_rect.tile;
+            return;
+        }
+    }
+
+    let (x, y, palette) = get_new_oam_base(app);
+    oams::add_oams(app, vec![create_oam(shape, size, tile_rect.tile, x, y, palette)]);
+}
+
+// Adds an OAM for every piece of the rectangle, laid out the same way they are on the sheet
+fn split_tile_rect(app: &mut Yanimator, tile_rect: TileRect) {
+    let (base_x, base_y, palette) = get_new_oam_base(app);
+
+    // Only split if every piece still fits in the -128 to 127 range
+    let new_oams: Option<Vec<OAM>> = oam_fitter::split_into_shapes(tile_rect.width, tile_rect.height)
+        .into_iter()
+        .map(|(x, y, shape, size)| Some(create_oam(
+            shape,
+            size,
+            tile_rect.tile + x + y * SHEET_WIDTH,
+            i8::try_from(base_x as i32 + x as i32 * 8).ok()?,
+            i8::try_from(base_y as i32 + y as i32 * 8).ok()?,
+            palette
+        )))
+        .collect();
+
+    app.spritesheet_panel.pending_split = None;
+
+    match new_oams {
+        Some(new_oams) => oams::add_oams(app, new_oams),
+        None => app.spritesheet_panel.split_error = Some(String::from("That would move OAMs outside of -128 to 127"))
+    }
+}
+
+// How many OAMs in every cel use each tile
+fn get_tile_usage_counts(app: &Yanimator, tile_count: usize) -> Vec<usize> {
+    let mut counts = vec![0; tile_count];
+
+    for oam in app.animation_cels.values().flat_map(|cel| cel.oams.iter()) {
+        for tile in oam.get_sprite_indexes_one_dimensional() {
+            if let Some(count) = counts.get_mut(tile) {
+                *count += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+// Every cel using the tile, with the indexes of the OAMs in it that do
+fn get_tile_users(app: &Yanimator, tile: usize) -> Vec<(String, Vec<usize>)> {
+    app.animation_cels
+        .iter()
+        .sorted_by_key(|(name, _)| *name)
+        .map(|(name, cel)| {
+            let oams = cel.oams
+                .iter()
+                .enumerate()
+                .filter(|(_, oam)| oam.get_sprite_indexes_one_dimensional().contains(&tile))
+                .map(|(i, _)| i)
+                .collect();
+
+            (name.clone(), oams)
+        })
+        .filter(|(_, oams): &(String, Vec<usize>)| !oams.is_empty())
+        .collect()
+}
+
+fn ui_tile_usage(ui: &mut Ui, app: &mut Yanimator) {
+    let tile = match app.spritesheet_panel.lookup_tile.or(app.spritesheet_panel.hovered_tile) {
+        Some(tile) => tile,
+        None => {
+            ui.weak("Hover over a tile to see what uses it, right click to keep it here");
+            return;
+        }
     };
 
-    let texture_sheet = match app.textures.get(app.spritesheet_palette) {
-        Some(texture_sheet) => texture_sheet,
+    let users = get_tile_users(app, tile);
+
+    ui.horizontal(|ui| {
+        let pinned = if app.spritesheet_panel.lookup_tile.is_some() { " (pinned)" } else { "" };
+        ui.strong(format!("Tile {}{}: used by {} cels", tile, pinned, users.len()));
+
+        if app.spritesheet_panel.lookup_tile.is_some() && ui.button("Unpin").clicked() {
+            app.spritesheet_panel.lookup_tile = None;
+        }
+    });
+
+    egui::ScrollArea::vertical()
+    .id_salt("tile_usage")
+    .max_height(150.0)
+    .show(ui, |ui| {
+        for (name, oam_ids) in &users {
+            let animations = app.animations
+                .iter()
+                .filter(|animation| animation.frames.iter().any(|frame| &frame.cell == name))
+                .map(|animation| animation.name.as_str())
+                .join(", ");
+
+            ui.horizontal_wrapped(|ui| {
+                if ui.selectable_label(app.editing_cell == *name, name).clicked() {
+                    app.state = AppState::CellEditor;
+                    app.edit_oam(name, oam_ids[0]);
+                }
+
+                ui.label(oam_ids.iter().map(|i| format!("OAM_{}", i)).join(", "));
+
+                if animations.is_empty() {
+                    ui.weak("not in any animation");
+                } else {
+                    ui.weak(format!("in {}", animations));
+                }
+            });
+        }
+    });
+
+    ui.separator();
+}
+
+fn ui_split_prompt(ui: &mut Ui, app: &mut Yanimator) {
+    if let Some(error) = &app.spritesheet_panel.split_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    let tile_rect = match app.spritesheet_panel.pending_split {
+        Some(tile_rect) => tile_rect,
         None => return
     };
 
+    let pieces = oam_fitter::split_into_shapes(tile_rect.width, tile_rect.height).len();
+
+    ui.horizontal(|ui| {
+        ui.label(format!("{}x{} tiles isn't an OAM size, split it into {} OAMs?", tile_rect.width, tile_rect.height, pieces));
+
+        if ui.button("Split").clicked() {
+            split_tile_rect(app, tile_rect);
+        }
+
+        if ui.button("Cancel").clicked() {
+            app.spritesheet_panel.pending_split = None;
+        }
+    });
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    let editing_oam = app.animation_cels.get(&app.editing_cell).and_then(|cell| cell.oams.get(app.editing_oam));
+
+    let palette = match (editing_oam, app.spritesheet_panel.use_oam_palette) {
+        (Some(oam), true) => oam.palette,
+        _ => app.spritesheet_palette
+    };
+    let used_tiles = editing_oam.map(|oam| oam.get_sprite_indexes_one_dimensional()).unwrap_or_default();
+    let locked = editing_oam.is_some_and(|oam| oam.locked);
+
     ui.label("Spritesheet");
     ui.horizontal(|ui| {
         ui.label("Preview Palette ID");
-        let mut upper_range = app.palette.palettes.len();
-            
-        if upper_range > 0 {
-            upper_range -= 1;
+        let upper_range = app.palette.palettes.len().saturating_sub(1);
+
+        ui.add_enabled(
+            !app.spritesheet_panel.use_oam_palette,
+            egui::DragValue::new(&mut app.spritesheet_palette).speed(0.2).range(0..=upper_range)
+        );
+        ui.checkbox(&mut app.spritesheet_panel.use_oam_palette, "Use OAM's");
+
+        ui.separator();
+
+        ui.add(egui::Slider::new(&mut app.spritesheet_panel.zoom, 0.5..=4.0).text("Zoom"));
+        ui.checkbox(&mut app.spritesheet_panel.show_indices, "Indices");
+        ui.checkbox(&mut app.spritesheet_panel.heatmap, "Heatmap");
+        ui.checkbox(&mut app.spritesheet_panel.show_usage, "Usage");
+
+        if ui.button("Edit Pixels").clicked() {
+            app.tile_editor_panel.open = true;
         }
-        ui.add(egui::DragValue::new(&mut app.spritesheet_palette).speed(0.2).range(0..=upper_range));
     });
-    
-
-    egui::Grid::new("spritesheet_grid").spacing(vec2(-SPRITE_SIZE,0.0)).show(ui, |ui| {
-        let mut i = 0;
-        
-        while i < texture_sheet.len() {
-            let source = match texture_sheet.get(i) {
-                Some(source) => source,
-                None => continue
-            };
-            
-            let mut texture = egui::Image::new(source).fit_to_exact_size(vec2(SPRITE_SIZE, SPRITE_SIZE));
-            
-            if oam.get_sprite_indexes_one_dimensional().iter().find(|&&x| x == i).is_some() {
-                texture = texture.tint(Color32::LIGHT_GREEN);
-                
+
+    ui_split_prompt(ui, app);
+
+    if app.spritesheet_panel.show_usage {
+        ui_tile_usage(ui, app);
+    }
+
+    if let Some(tile) = app.spritesheet_panel.hovered_tile {
+        let uses = app.animation_cels
+            .get(&app.editing_cell)
+            .map(|cell| cell.oams.iter().filter(|oam| oam.get_sprite_indexes_one_dimensional().contains(&tile)).count())
+            .unwrap_or(0);
+
+        ui.label(format!(
+            "Tile {} (0x{:03x}), column {}, row {}, used by {} OAMs in this cel",
+            tile, tile, tile % SHEET_WIDTH, tile / SHEET_WIDTH, uses
+        ));
+    } else if let Some(oam) = app.animation_cels.get(&app.editing_cell).and_then(|cell| cell.oams.get(app.editing_oam)) {
+        ui.label(format!("OAM_{}: {} at tile {}", app.editing_oam, properties::get_size_string_with_shape(&oam.size, &oam.shape), oam.tile));
+    }
+
+    ui.weak("Click to set the tile, drag a rectangle to set the shape too, hold ctrl while dragging to add a new OAM instead");
+
+
+    let texture_sheet = match app.textures.get(palette) {
+        Some(texture_sheet) => texture_sheet,
+        None => return
+    };
+
+    let tile_count = texture_sheet.len();
+    if tile_count == 0 {return;}
+
+    let tile_size = SPRITE_SIZE * app.spritesheet_panel.zoom;
+    let rows = tile_count.div_ceil(SHEET_WIDTH);
+
+    let usage_counts = if app.spritesheet_panel.heatmap { get_tile_usage_counts(app, tile_count) } else { Vec::new() };
+    let max_usage = usage_counts.iter().copied().max().unwrap_or(0).max(1);
+
+    let mut hovered_tile = None;
+    let mut lookup_tile = app.spritesheet_panel.lookup_tile;
+    let mut released_rect = None;
+    let mut clicked_tile = None;
+
+    egui::ScrollArea::both()
+    .auto_shrink(false)
+    .show(ui, |ui| {
+        let (rect, response) = ui.allocate_exact_size(vec2(SHEET_WIDTH as f32, rows as f32) * tile_size, Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+
+        let get_tile = |pos: Pos2| {
+            let column = ((pos.x - rect.min.x) / tile_size).clamp(0.0, (SHEET_WIDTH - 1) as f32) as usize;
+            let row = ((pos.y - rect.min.y) / tile_size).clamp(0.0, (rows - 1) as f32) as usize;
+            column + row * SHEET_WIDTH
+        };
+
+        let get_tile_rect = |tile: usize, width: usize, height: usize| Rect::from_min_size(
+            rect.min + vec2((tile % SHEET_WIDTH) as f32, (tile / SHEET_WIDTH) as f32) * tile_size,
+            vec2(width as f32, height as f32) * tile_size
+        );
+
+        for (i, texture) in texture_sheet.iter().enumerate() {
+            let tint = if used_tiles.contains(&i) { Color32::LIGHT_GREEN } else { Color32::WHITE };
+            let tile_rect = get_tile_rect(i, 1, 1);
+
+            painter.image(texture.id(), tile_rect, Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)), tint);
+
+            // Blue for few uses up to red for the most, unused tiles are crossed out
+            if let Some(&count) = usage_counts.get(i) {
+                if count == 0 {
+                    painter.rect_filled(tile_rect, 0, Color32::from_black_alpha(160));
+                    painter.line_segment([tile_rect.left_top(), tile_rect.right_bottom()], Stroke::new(1.0, Color32::RED));
+                } else {
+                    let heat = count as f32 / max_usage as f32;
+                    painter.rect_filled(tile_rect, 0, Color32::from_rgba_unmultiplied((255.0 * heat) as u8, 64, (255.0 * (1.0 - heat)) as u8, 110));
+                }
+            }
+
+            if app.spritesheet_panel.show_indices {
+                painter.text(tile_rect.left_top() + vec2(1.0, 0.0), Align2::LEFT_TOP, i.to_string(), FontId::monospace(tile_size / 3.0), Color32::WHITE);
             }
+        }
+
+        hovered_tile = response.hover_pos().map(get_tile).filter(|&tile| tile < tile_count);
 
-            let sprite = ui.add(texture);
-            if sprite.clicked() {
-                oam.tile = i;
+        if let Some(tile) = hovered_tile {
+            if app.spritesheet_panel.heatmap {
+                response.clone().on_hover_text_at_pointer(format!("{} uses", usage_counts[tile]));
             }
+        }
+
+        if response.clicked() {
+            clicked_tile = hovered_tile;
+        }
+
+        if response.secondary_clicked() && app.spritesheet_panel.show_usage {
+            lookup_tile = if hovered_tile == lookup_tile { None } else { hovered_tile };
+        }
+
+        if let Some(tile) = lookup_tile.filter(|_| app.spritesheet_panel.show_usage) {
+            painter.rect_stroke(get_tile_rect(tile, 1, 1), 0, Stroke::new(2.0, Color32::LIGHT_BLUE), egui::StrokeKind::Inside);
+        }
+
+        if response.drag_started() {
+            app.spritesheet_panel.drag_start = response.interact_pointer_pos().map(get_tile);
+        }
+
+        if let (Some(start), Some(current)) = (app.spritesheet_panel.drag_start, response.interact_pointer_pos().map(get_tile)) {
+            let (left, right) = ((start % SHEET_WIDTH).min(current % SHEET_WIDTH), (start % SHEET_WIDTH).max(current % SHEET_WIDTH));
+            let (top, bottom) = ((start / SHEET_WIDTH).min(current / SHEET_WIDTH), (start / SHEET_WIDTH).max(current / SHEET_WIDTH));
+
+            let tile_rect = TileRect {
+                tile: left + top * SHEET_WIDTH,
+                width: right - left + 1,
+                height: bottom - top + 1
+            };
 
-            if (i + 1) % 32 == 0 {
-                ui.end_row();
+            let color = if oam_fitter::get_shape_for_size(tile_rect.width, tile_rect.height).is_some() { Color32::YELLOW } else { Color32::RED };
+            painter.rect_stroke(get_tile_rect(tile_rect.tile, tile_rect.width, tile_rect.height), 0, Stroke::new(2.0, color), egui::StrokeKind::Inside);
+
+            // A shaky click that never left its tile only sets the tile, like a click
+            if response.drag_stopped() {
+                if tile_rect.width == 1 && tile_rect.height == 1 {
+                    clicked_tile = Some(tile_rect.tile).filter(|&tile| tile < tile_count);
+                } else {
+                    released_rect = Some(tile_rect);
+                }
             }
+        }
 
-            i += 1;
+        if response.drag_stopped() {
+            app.spritesheet_panel.drag_start = None;
         }
     });
 
-    ui.add_space(ui.available_height());
-}
\ No newline at end of file
+    app.spritesheet_panel.hovered_tile = hovered_tile;
+    app.spritesheet_panel.lookup_tile = lookup_tile;
+
+    if let Some(tile) = clicked_tile {
+        let oam = app.animation_cels.get_mut(&app.editing_cell).and_then(|cell| cell.oams.get_mut(app.editing_oam));
+
+        if let Some(oam) = oam.filter(|_| !locked) {
+            oam.tile = tile;
+        }
+    }
+
+    if let Some(tile_rect) = released_rect {
+        let create_new = ui.input(|input| input.modifiers.command);
+        use_tile_rect(app, tile_rect, create_new);
+    }
+}
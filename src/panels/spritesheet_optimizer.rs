@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use egui::Ui;
+use rfd::FileDialog;
+
+use crate::{panels::menu_bar, sheet_optimizer::{self, OptimizedSheet}, Yanimator};
+
+pub struct SpritesheetOptimizerPanel {
+    pub open: bool,
+    result: Option<OptimizedSheet>,
+    message: Option<String>
+}
+
+impl SpritesheetOptimizerPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            result: None,
+            message: None
+        }
+    }
+}
+
+fn apply_and_save(ui: &mut Ui, app: &mut Yanimator) {
+    let file_path: PathBuf = match FileDialog::new()
+    .add_filter("4bpp spritesheet", &["4bpp"])
+    .set_directory("/")
+    .set_title("Select save location")
+    .save_file() {
+        Some(file) => file,
+        None => return
+    };
+
+    let path_str = match file_path.to_str() {
+        Some(path) => path,
+        None => return
+    };
+
+    // Redone in case cels were edited since the analysis
+    let result = sheet_optimizer::optimize(&app.spritesheet, app.animation_cels.values());
+
+    if let Some(error) = result.get_apply_error() {
+        app.spritesheet_optimizer_panel.message = Some(error);
+        return;
+    }
+
+    if let Err(err) = result.spritesheet.to_4bpp(path_str) {
+        app.spritesheet_optimizer_panel.message = Some(format!("Couldn't save spritesheet: {}", err));
+        return;
+    }
+
+    // The report is written next to the sheet, like "sheet.4bpp" and "sheet_report.txt"
+    let report_path = file_path.with_file_name(format!(
+        "{}_report.txt",
+        file_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("spritesheet")
+    ));
+    let report_result = std::fs::write(report_path, result.get_report());
+
+    result.remap_cels(app.animation_cels.values_mut());
+    app.spritesheet = result.spritesheet;
+    app.spritesheet_optimizer_panel.result = None;
+    menu_bar::load_texture_handles(ui, app);
+
+    app.spritesheet_optimizer_panel.message = Some(match report_result {
+        Ok(()) => String::from("Spritesheet optimized and saved"),
+        Err(err) => format!("Spritesheet optimized and saved, but couldn't save the report: {}", err)
+    });
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    ui.label("Removes tiles that aren't used by any OAM and stores identical (or flipped) blocks of tiles once, then updates every OAM to match.");
+
+    if ui.add_enabled(!app.spritesheet.sprites.is_empty(), egui::Button::new("Analyze")).clicked() {
+        app.spritesheet_optimizer_panel.result = Some(sheet_optimizer::optimize(&app.spritesheet, app.animation_cels.values()));
+        app.spritesheet_optimizer_panel.message = None;
+    }
+
+    if let Some(result) = &app.spritesheet_optimizer_panel.result {
+        ui.separator();
+
+        egui::Grid::new("spritesheet_optimizer_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Tiles before");
+                ui.label(result.old_tile_count.to_string());
+                ui.end_row();
+
+                ui.label("Tiles after");
+                ui.label(result.spritesheet.sprites.len().to_string());
+                ui.end_row();
+
+                ui.label("Unused tiles");
+                ui.label(result.unused_tiles.to_string());
+                ui.end_row();
+
+                ui.label("Reused blocks");
+                ui.label(format!("{} ({} flipped)", result.duplicate_blocks, result.flipped_blocks));
+                ui.end_row();
+
+                ui.label("Bytes saved");
+                ui.label(result.get_bytes_saved().to_string());
+                ui.end_row();
+            });
+
+        ui.separator();
+
+        let apply_error = result.get_apply_error();
+
+        if let Some(error) = &apply_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if ui.add_enabled(apply_error.is_none(), egui::Button::new("Apply and Save .4bpp...")).clicked() {
+            apply_and_save(ui, app);
+        }
+    }
+
+    if let Some(message) = &app.spritesheet_optimizer_panel.message {
+        ui.label(message);
+    }
+}
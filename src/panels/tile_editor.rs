@@ -0,0 +1,295 @@
+use std::path::PathBuf;
+
+use egui::{vec2, Color32, Rect, Sense, Stroke, Ui};
+use rfd::FileDialog;
+
+use crate::{anim_parser::OAM, panels::menu_bar, Yanimator};
+
+const MIN_ZOOM: f32 = 4.0;
+const MAX_ZOOM: f32 = 32.0;
+const SWATCH_SIZE: f32 = 18.0;
+
+#[derive(PartialEq)]
+pub enum TileTool {
+    Pencil,
+    Fill,
+    Eyedropper
+}
+
+pub struct TileEditorPanel {
+    pub open: bool,
+    tool: TileTool,
+    color: u8,
+    zoom: f32,
+    pixel_grid_enabled: bool,
+    save_error: Option<String>
+}
+
+impl TileEditorPanel {
+    pub fn init() -> Self {
+        Self {
+            open: false,
+            tool: TileTool::Pencil,
+            color: 1,
+            zoom: 12.0,
+            pixel_grid_enabled: true,
+            save_error: None
+        }
+    }
+}
+
+fn get_pixel(app: &Yanimator, oam: &OAM, x: usize, y: usize) -> Option<u8> {
+    let (tile, pixel) = oam.get_pixel_location(x, y)?;
+    app.spritesheet.sprites.get(tile).map(|sprite| sprite.pixels[pixel])
+}
+
+// Returns the tile that was changed, if any
+fn set_pixel(app: &mut Yanimator, oam: &OAM, x: usize, y: usize, color: u8) -> Option<usize> {
+    let (tile, pixel) = oam.get_pixel_location(x, y)?;
+    let sprite = app.spritesheet.sprites.get_mut(tile)?;
+
+    if sprite.pixels[pixel] == color {return None;}
+    sprite.pixels[pixel] = color;
+
+    Some(tile)
+}
+
+// Fills the area of the OAM connected to (x, y) that has the same color
+fn fill(app: &mut Yanimator, oam: &OAM, x: usize, y: usize, color: u8) -> Vec<usize> {
+    let (width, height) = oam.get_width_and_height();
+    let (width, height) = (width * 8, height * 8);
+    let mut changed_tiles = Vec::new();
+
+    let target = match get_pixel(app, oam, x, y) {
+        Some(target) => target,
+        None => return changed_tiles
+    };
+    if target == color {return changed_tiles;}
+
+    let mut stack = vec![(x, y)];
+
+    while let Some((x, y)) = stack.pop() {
+        if get_pixel(app, oam, x, y) != Some(target) {continue;}
+
+        if let Some(tile) = set_pixel(app, oam, x, y, color) {
+            if !changed_tiles.contains(&tile) {
+                changed_tiles.push(tile);
+            }
+        }
+
+        if x > 0 {stack.push((x - 1, y));}
+        if y > 0 {stack.push((x, y - 1));}
+        if x + 1 < width {stack.push((x + 1, y));}
+        if y + 1 < height {stack.push((x, y + 1));}
+    }
+
+    changed_tiles
+}
+
+fn save_spritesheet(app: &mut Yanimator, png: bool, palette_id: usize) {
+    let (filter_name, extension) = if png { ("Indexed PNG", "png") } else { ("4bpp spritesheet", "4bpp") };
+
+    let file_path: PathBuf = match FileDialog::new()
+    .add_filter(filter_name, &[extension])
+    .set_directory("/")
+    .set_title("Select save location")
+    .save_file() {
+        Some(file) => file,
+        None => return
+    };
+
+    let path_str = match file_path.to_str() {
+        Some(path) => path,
+        None => return
+    };
+
+    let result = if png {
+        match app.palette.palettes.get(palette_id) {
+            Some(pal) => app.spritesheet.to_png(path_str, pal).map_err(|err| err.to_string()),
+            None => Err(String::from("no palette loaded"))
+        }
+    } else {
+        app.spritesheet.to_4bpp(path_str).map_err(|err| err.to_string())
+    };
+
+    app.tile_editor_panel.save_error = result.err().map(|err| format!("Couldn't save spritesheet: {}", err));
+}
+
+fn ui_colors(ui: &mut Ui, app: &mut Yanimator, palette_id: usize) {
+    let pal = match app.palette.palettes.get(palette_id) {
+        Some(pal) => pal,
+        None => return
+    };
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 2.0;
+
+        for (i, color) in pal.iter().enumerate() {
+            let (rect, response) = ui.allocate_exact_size(vec2(SWATCH_SIZE, SWATCH_SIZE), Sense::click());
+
+            ui.painter().rect_filled(rect, 0, Color32::from_rgb(color.r, color.g, color.b));
+
+            let stroke = if app.tile_editor_panel.color as usize == i {
+                Stroke::new(2.0, ui.visuals().selection.stroke.color)
+            } else {
+                Stroke::new(1.0, Color32::from_gray(60))
+            };
+            ui.painter().rect_stroke(rect, 0, stroke, egui::StrokeKind::Inside);
+
+            let hover_text = if i == 0 { String::from("0 (transparent)") } else { i.to_string() };
+            if response.on_hover_text(hover_text).clicked() {
+                app.tile_editor_panel.color = i as u8;
+            }
+        }
+    });
+}
+
+fn ui_canvas(ui: &mut Ui, app: &mut Yanimator, oam: &OAM) {
+    let (width, height) = oam.get_width_and_height();
+    let (width, height) = (width * 8, height * 8);
+    let zoom = app.tile_editor_panel.zoom;
+
+    let (rect, response) = ui.allocate_exact_size(vec2(width as f32 * zoom, height as f32 * zoom), Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+
+    painter.rect_filled(rect, 0, Color32::from_gray(40));
+
+    let pal = app.palette.palettes.get(oam.palette);
+
+    for y in 0..height {
+        for x in 0..width {
+            let color_id = match get_pixel(app, oam, x, y) {
+                Some(color_id) => color_id,
+                None => continue
+            };
+
+            if color_id == 0 {continue;}
+
+            if let Some(color) = pal.and_then(|pal| pal.get(color_id as usize)) {
+                let pixel_rect = Rect::from_min_size(rect.min + vec2(x as f32, y as f32) * zoom, vec2(zoom, zoom));
+                painter.rect_filled(pixel_rect, 0, Color32::from_rgb(color.r, color.g, color.b));
+            }
+        }
+    }
+
+    let grid_stroke = Stroke::new(1.0, Color32::from_white_alpha(20));
+    let tile_stroke = Stroke::new(1.0, Color32::from_white_alpha(80));
+
+    for x in 0..=width {
+        if x % 8 != 0 && !app.tile_editor_panel.pixel_grid_enabled {continue;}
+        let line_x = rect.min.x + x as f32 * zoom;
+        painter.vline(line_x, rect.y_range(), if x % 8 == 0 { tile_stroke } else { grid_stroke });
+    }
+
+    for y in 0..=height {
+        if y % 8 != 0 && !app.tile_editor_panel.pixel_grid_enabled {continue;}
+        let line_y = rect.min.y + y as f32 * zoom;
+        painter.hline(rect.x_range(), line_y, if y % 8 == 0 { tile_stroke } else { grid_stroke });
+    }
+
+    let hovered_pixel = response.hover_pos().map(|pos| {
+        let local = (pos - rect.min) / zoom;
+        (local.x.max(0.0) as usize, local.y.max(0.0) as usize)
+    });
+
+    if let Some((x, y)) = hovered_pixel {
+        let pixel_rect = Rect::from_min_size(rect.min + vec2(x as f32, y as f32) * zoom, vec2(zoom, zoom));
+        painter.rect_stroke(pixel_rect, 0, Stroke::new(1.0, Color32::WHITE), egui::StrokeKind::Inside);
+
+        if let Some((tile, pixel)) = oam.get_pixel_location(x, y) {
+            response.clone().on_hover_text_at_pointer(format!("Tile {} ({}, {})", tile, pixel % 8, pixel / 8));
+        }
+    }
+
+    let (x, y) = match hovered_pixel {
+        Some(pixel) => pixel,
+        None => return
+    };
+
+    let color = app.tile_editor_panel.color;
+    let mut changed_tiles = Vec::new();
+
+    match app.tile_editor_panel.tool {
+        TileTool::Pencil => {
+            if response.is_pointer_button_down_on() && ui.input(|i| i.pointer.primary_down()) {
+                changed_tiles.extend(set_pixel(app, oam, x, y, color));
+            }
+        },
+        TileTool::Fill => {
+            if response.clicked() {
+                changed_tiles = fill(app, oam, x, y, color);
+            }
+        },
+        TileTool::Eyedropper => {
+            if response.clicked() {
+                if let Some(color) = get_pixel(app, oam, x, y) {
+                    app.tile_editor_panel.color = color;
+                    app.tile_editor_panel.tool = TileTool::Pencil;
+                }
+            }
+        }
+    }
+
+    // Right click always picks the color, like most pixel editors
+    if response.secondary_clicked() {
+        if let Some(color) = get_pixel(app, oam, x, y) {
+            app.tile_editor_panel.color = color;
+        }
+    }
+
+    for tile in changed_tiles {
+        menu_bar::update_sprite_textures(app, tile);
+    }
+}
+
+pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    let oam = match app.animation_cels.get(&app.editing_cell).and_then(|cell| cell.oams.get(app.editing_oam)) {
+        Some(oam) => oam.clone(),
+        None => {
+            ui.label("Select an OAM in the cel editor to edit its tiles");
+            return;
+        }
+    };
+
+    if app.spritesheet.sprites.is_empty() || app.palette.palettes.is_empty() {
+        ui.label("Load a spritesheet and palette first");
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut app.tile_editor_panel.tool, TileTool::Pencil, "Pencil");
+        ui.selectable_value(&mut app.tile_editor_panel.tool, TileTool::Fill, "Fill");
+        ui.selectable_value(&mut app.tile_editor_panel.tool, TileTool::Eyedropper, "Eyedropper");
+
+        ui.separator();
+
+        ui.add(egui::Slider::new(&mut app.tile_editor_panel.zoom, MIN_ZOOM..=MAX_ZOOM).text("Zoom"));
+        ui.checkbox(&mut app.tile_editor_panel.pixel_grid_enabled, "Pixel Grid");
+    });
+
+    ui.label(format!("Palette {}", oam.palette));
+    ui_colors(ui, app, oam.palette);
+
+    ui.separator();
+
+    egui::ScrollArea::both()
+    .show(ui, |ui| {
+        ui_canvas(ui, app, &oam);
+    });
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.button("Save .4bpp").clicked() {
+            save_spritesheet(app, false, oam.palette);
+        }
+
+        if ui.button("Save .png").clicked() {
+            save_spritesheet(app, true, oam.palette);
+        }
+    });
+
+    if let Some(message) = &app.tile_editor_panel.save_error {
+        ui.colored_label(ui.visuals().error_fg_color, message);
+    }
+}
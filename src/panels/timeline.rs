@@ -1,8 +1,15 @@
-use egui::{include_image, pos2, vec2, Color32, Image, ImageButton, InputState, Key, PointerButton, Rect, Response, Scene, Stroke, Ui};
+use egui::{include_image, pos2, vec2, Color32, Image, ImageButton, InputState, Key, PointerButton, Pos2, Rect, Response, Scene, Stroke, Ui};
 
-use crate::Yanimator;
+use crate::{anim_parser::CopiedAnimationFrame, Yanimator};
 
 
+#[derive(PartialEq, Clone, Copy)]
+pub enum PlaybackRate {
+    Hardware,
+    Sixty,
+    Custom
+}
+
 #[derive(Debug)]
 pub struct Keyframe {
     input_rect: Rect,
@@ -13,24 +20,43 @@ pub struct Keyframe {
 
 pub struct Timeline {
     pub rect: Rect,
+    track_rect: Rect,
     zoom: f32,
     scroll: f32,
     pub playing: bool,
+    playback_rate: PlaybackRate,
+    custom_playback_rate: f32,
+    playback_speed: f32,
+    // Fraction of a frame that has passed since the last frame was shown
+    playback_accumulator: f32,
+    dropped_frames_warning_enabled: bool,
+    dropped_frames: usize,
     pub keyframes: Vec<Keyframe>,
     start_drag_x: f32,
-    dragging: bool
+    dragging: bool,
+    box_select_start: Option<Pos2>,
+    pub clipboard: Vec<CopiedAnimationFrame>
 }
 
 impl Timeline {
     pub fn init() -> Timeline {
         Timeline { 
             rect: Rect::ZERO,
+            track_rect: Rect::ZERO,
             zoom: 10.0,
             scroll: 0.0,
             playing: false,
+            playback_rate: PlaybackRate::Hardware,
+            custom_playback_rate: 30.0,
+            playback_speed: 1.0,
+            playback_accumulator: 0.0,
+            dropped_frames_warning_enabled: false,
+            dropped_frames: 0,
             keyframes: Vec::new(),
             start_drag_x: 0.0,
-            dragging: true
+            dragging: true,
+            box_select_start: None,
+            clipboard: Vec::new()
         }
     }
 
@@ -59,10 +85,233 @@ impl Timeline {
             None => false
         }
     }
+
+    pub fn get_playback_rate_hz(&self) -> f32 {
+        match self.playback_rate {
+            PlaybackRate::Hardware => GBA_REFRESH_RATE,
+            PlaybackRate::Sixty => 60.0,
+            PlaybackRate::Custom => self.custom_playback_rate
+        }
+    }
+
+    // Returns how many frames playback should move forward by
+    pub fn advance_playback(&mut self, elapsed: f32) -> usize {
+        if !self.playing {
+            self.playback_accumulator = 0.0;
+            return 0;
+        }
+
+        self.playback_accumulator += elapsed * self.get_playback_rate_hz() * self.playback_speed;
+
+        // Don't try to catch up after a long stall (e.g. the window being dragged around)
+        if self.playback_accumulator > MAX_CATCH_UP_FRAMES {
+            self.dropped_frames += (self.playback_accumulator - MAX_CATCH_UP_FRAMES) as usize;
+            self.playback_accumulator = MAX_CATCH_UP_FRAMES;
+        }
+
+        let frames = self.playback_accumulator.floor();
+        self.playback_accumulator -= frames;
+
+        // Skipping frames is expected when playing faster than the refresh rate
+        if frames > 1.0 && self.playback_speed <= 1.0 {
+            self.dropped_frames += frames as usize - 1;
+        }
+
+        frames as usize
+    }
+
+    pub fn get_selected_keyframe_ids(&self) -> Vec<usize> {
+        self.keyframes.iter().filter(|k| k.selected).map(|k| k.id).collect()
+    }
+
+    pub fn select_keyframes(&mut self, frame_ids: &[usize]) {
+        for keyframe in &mut self.keyframes {
+            keyframe.selected = frame_ids.contains(&keyframe.id);
+        }
+
+        for &id in frame_ids {
+            if !self.keyframes.iter().any(|k| k.id == id) {
+                self.keyframes.push(Keyframe { input_rect: Rect::NOTHING, selected: true, hovered: false, id });
+            }
+        }
+    }
 }
 
+// 16.78 MHz / 280896 cycles per frame
+const GBA_REFRESH_RATE: f32 = 59.7275;
+const MAX_CATCH_UP_FRAMES: f32 = 10.0;
+const PLAYBACK_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
 const KEYFRAME_SIZE: f32 = 30.0;
 const SCROLL_SPEED: f32 = 10.0;
+const ZOOM_SPEED: f32 = 1.1;
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 100.0;
+const SCROLLBAR_HEIGHT: f32 = 8.0;
+
+// Minimum distance in pixels between two labeled ticks on the ruler
+const RULER_LABEL_SPACING: f32 = 40.0;
+const RULER_TICK_STEPS: [usize; 10] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000];
+
+fn frame_to_x(timeline: &Timeline, frame: f32) -> f32 {
+    frame * timeline.zoom + timeline.scroll + KEYFRAME_SIZE / 2.0
+}
+
+fn x_to_frame(timeline: &Timeline, x: f32) -> f32 {
+    (x - timeline.scroll - KEYFRAME_SIZE / 2.0) / timeline.zoom
+}
+
+fn get_ruler_tick_step(zoom: f32) -> usize {
+    for step in RULER_TICK_STEPS {
+        if step as f32 * zoom >= RULER_LABEL_SPACING {
+            return step;
+        }
+    }
+
+    RULER_TICK_STEPS[RULER_TICK_STEPS.len() - 1]
+}
+
+fn get_ruler_subtick_step(step: usize) -> Option<usize> {
+    if step.is_multiple_of(5) {
+        Some(step / 5)
+    } else if step.is_multiple_of(2) {
+        Some(step / 2)
+    } else {
+        None
+    }
+}
+
+// Keeps the frame under the mouse cursor in place
+fn zoom_at(timeline: &mut Timeline, x: f32, zoom_factor: f32) {
+    let frame = x_to_frame(timeline, x);
+
+    timeline.zoom = (timeline.zoom * zoom_factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    timeline.scroll = x - KEYFRAME_SIZE / 2.0 - frame * timeline.zoom;
+}
+
+pub fn zoom_to_fit(app: &mut Yanimator) {
+    let animation = match app.animations.get(app.animation_id) {
+        Some(animation) => animation,
+        None => return
+    };
+
+    let total_frames = animation.get_total_frames().max(1);
+
+    app.timeline.zoom = ((app.timeline.rect.width() - KEYFRAME_SIZE) / total_frames as f32).clamp(MIN_ZOOM, MAX_ZOOM);
+    app.timeline.scroll = app.timeline.rect.min.x;
+}
+
+fn get_scrollbar_rect(timeline: &Timeline) -> Rect {
+    Rect::from_min_max(
+        pos2(timeline.rect.min.x, timeline.rect.max.y - SCROLLBAR_HEIGHT),
+        timeline.rect.max
+    )
+}
+
+fn draw_scrollbar(ui: &mut Ui, timeline: &mut Timeline, animation_end: usize) {
+    let bar_rect = get_scrollbar_rect(timeline);
+
+    let view_width = timeline.rect.width();
+    let content_width = (animation_end as f32 * timeline.zoom + KEYFRAME_SIZE).max(view_width);
+    let offset = (timeline.rect.min.x - timeline.scroll).clamp(0.0, content_width - view_width);
+
+    let thumb_width = (view_width / content_width * bar_rect.width()).max(SCROLLBAR_HEIGHT * 2.0);
+    let thumb_x = offset / content_width * bar_rect.width();
+    let thumb_rect = Rect::from_min_size(pos2(bar_rect.min.x + thumb_x, bar_rect.min.y), vec2(thumb_width, SCROLLBAR_HEIGHT));
+
+    let response = ui.interact(bar_rect, ui.id().with("timeline_scrollbar"), egui::Sense::click_and_drag());
+
+    if response.dragged() {
+        timeline.scroll -= response.drag_delta().x * content_width / bar_rect.width();
+    }
+
+    let thumb_color = if response.hovered() || response.dragged() {
+        Color32::from_gray(120)
+    } else {
+        Color32::from_gray(90)
+    };
+
+    ui.painter().rect_filled(bar_rect, SCROLLBAR_HEIGHT / 2.0, Color32::from_gray(40));
+    ui.painter().rect_filled(thumb_rect, SCROLLBAR_HEIGHT / 2.0, thumb_color);
+}
+
+// First line of keyframes copied to the system clipboard, the rest is json
+const CLIPBOARD_HEADER: &str = "Yanimator Keyframes";
+
+pub fn copy_keyframes(app: &mut Yanimator) {
+    let animation = match app.animations.get(app.animation_id) {
+        Some(animation) => animation,
+        None => return
+    };
+
+    let copied_frames = animation.copy_anim_frames(&app.timeline.get_selected_keyframe_ids());
+    if copied_frames.is_empty() {return;}
+
+    if let Ok(json) = serde_json::to_string(&copied_frames) {
+        app.clipboard_text = Some(format!("{}\n{}", CLIPBOARD_HEADER, json));
+    }
+
+    app.timeline.clipboard = copied_frames;
+}
+
+pub fn cut_keyframes(app: &mut Yanimator) {
+    copy_keyframes(app);
+    remove_selected_keyframes(app);
+}
+
+pub fn paste_keyframes(app: &mut Yanimator) {
+    let animation = match app.animations.get_mut(app.animation_id) {
+        Some(animation) => animation,
+        None => return
+    };
+
+    // Keyframes pasted from another project might use cels that don't exist here
+    let copied_frames: Vec<CopiedAnimationFrame> = app.timeline.clipboard
+        .iter()
+        .filter(|frame| app.animation_cels.contains_key(&frame.cell))
+        .cloned()
+        .collect();
+
+    let pasted_ids = animation.paste_anim_frames(&copied_frames, app.frames as isize);
+    app.timeline.select_keyframes(&pasted_ids);
+}
+
+pub fn select_all_keyframes(app: &mut Yanimator) {
+    if let Some(animation) = app.animations.get(app.animation_id) {
+        let frame_ids: Vec<usize> = animation.frames.iter().map(|f| f.id).collect();
+        app.timeline.select_keyframes(&frame_ids);
+    }
+}
+
+pub fn invert_keyframe_selection(app: &mut Yanimator) {
+    if let Some(animation) = app.animations.get(app.animation_id) {
+        let selected_ids = app.timeline.get_selected_keyframe_ids();
+        let frame_ids: Vec<usize> = animation.frames
+            .iter()
+            .map(|f| f.id)
+            .filter(|id| !selected_ids.contains(id))
+            .collect();
+
+        app.timeline.select_keyframes(&frame_ids);
+    }
+}
+
+fn read_clipboard_text(text: &str) -> Option<Vec<CopiedAnimationFrame>> {
+    let json = text.strip_prefix(CLIPBOARD_HEADER)?;
+
+    serde_json::from_str(json.trim()).ok()
+}
+
+fn remove_selected_keyframes(app: &mut Yanimator) {
+    let animation = match app.animations.get_mut(app.animation_id) {
+        Some(animation) => animation,
+        None => return
+    };
+
+    for id in app.timeline.get_selected_keyframe_ids() {
+        animation.remove_anim_frame(id);
+    }
+}
 
 fn draw_keyframe(ui: &mut Ui, height: f32, timeline: &mut Timeline, pos: f32, i: usize) -> Response {
     let keyframe_rect = egui::Rect::from_min_size(
@@ -159,6 +408,47 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
 
             animation.update_duration();
         }
+
+        if ui.button("Zoom to Fit").on_hover_text("F").clicked() {
+            zoom_to_fit(app);
+        }
+
+        ui.separator();
+
+        egui::ComboBox::from_id_salt("playback_rate_dropdown")
+            .selected_text(match app.timeline.playback_rate {
+                PlaybackRate::Hardware => "59.73 Hz (GBA)",
+                PlaybackRate::Sixty => "60 Hz",
+                PlaybackRate::Custom => "Custom"
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut app.timeline.playback_rate, PlaybackRate::Hardware, "59.73 Hz (GBA)");
+                ui.selectable_value(&mut app.timeline.playback_rate, PlaybackRate::Sixty, "60 Hz");
+                ui.selectable_value(&mut app.timeline.playback_rate, PlaybackRate::Custom, "Custom");
+            });
+
+        if app.timeline.playback_rate == PlaybackRate::Custom {
+            ui.add(egui::DragValue::new(&mut app.timeline.custom_playback_rate).range(1.0..=240.0).suffix(" Hz"));
+        }
+
+        egui::ComboBox::from_id_salt("playback_speed_dropdown")
+            .selected_text(format!("{}x", app.timeline.playback_speed))
+            .show_ui(ui, |ui| {
+                for speed in PLAYBACK_SPEEDS {
+                    ui.selectable_value(&mut app.timeline.playback_speed, speed, format!("{}x", speed));
+                }
+            });
+
+        ui.checkbox(&mut app.timeline.dropped_frames_warning_enabled, "Dropped Frame Warnings");
+
+        if app.timeline.dropped_frames_warning_enabled && app.timeline.dropped_frames > 0 {
+            let warning = ui.colored_label(ui.visuals().warn_fg_color, format!("{} dropped frames", app.timeline.dropped_frames))
+                .on_hover_text("Playback fell behind and had to skip frames, click to reset");
+
+            if warning.interact(egui::Sense::click()).clicked() {
+                app.timeline.dropped_frames = 0;
+            }
+        }
     });
 
     ui.separator();
@@ -170,6 +460,12 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
             animation_end = animation.get_total_frames();
         }
 
+        // Stops above the scrollbar so dragging it doesn't start a box selection
+        app.timeline.track_rect = Rect::from_min_max(
+            pos2(app.timeline.rect.min.x, ui.cursor().min.y + KEYFRAME_SIZE),
+            app.timeline.rect.max - vec2(0.0, SCROLLBAR_HEIGHT)
+        );
+
         ui.painter().rect_filled(
             Rect::from_min_size(
                 pos2(app.timeline.scroll + KEYFRAME_SIZE / 2.0, ui.cursor().min.y + KEYFRAME_SIZE), 
@@ -177,22 +473,35 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
             ), 
             0, Color32::from_gray(50));
 
-        for i in 0..animation_end {
-            if i % 10 != 0 { continue; }
-            
-            ui.painter().line_segment(
-                [
-                    pos2(i as f32 * app.timeline.zoom + KEYFRAME_SIZE / 2.0 + app.timeline.scroll, ui.cursor().min.y + KEYFRAME_SIZE),
-                    pos2(i as f32 * app.timeline.zoom + KEYFRAME_SIZE / 2.0 + app.timeline.scroll, ui.cursor().min.y + height)
-                ],
-                Stroke::new(2.0, Color32::from_gray(70))
-            );
-            
-            ui.put(egui::Rect::from_min_size(
-                pos2(i as f32 * app.timeline.zoom + app.timeline.scroll, ui.cursor().min.y), vec2(KEYFRAME_SIZE, KEYFRAME_SIZE)
-            ), |ui: &mut Ui| {
-                ui.label(format!("{}", i))
-            });
+        let ruler_y = ui.cursor().min.y;
+        let tick_step = get_ruler_tick_step(app.timeline.zoom);
+        let subtick_step = get_ruler_subtick_step(tick_step);
+
+        let first_visible = x_to_frame(&app.timeline, app.timeline.rect.min.x).max(0.0) as usize;
+        let last_visible = (x_to_frame(&app.timeline, app.timeline.rect.max.x).max(0.0) as usize).min(animation_end);
+
+        for i in first_visible..last_visible {
+            let x = frame_to_x(&app.timeline, i as f32);
+
+            if i % tick_step == 0 {
+                ui.painter().line_segment(
+                    [pos2(x, ruler_y + KEYFRAME_SIZE / 2.0), pos2(x, ruler_y + height)],
+                    Stroke::new(2.0, Color32::from_gray(70))
+                );
+
+                ui.painter().text(
+                    pos2(x, ruler_y),
+                    egui::Align2::CENTER_TOP,
+                    format!("{}", i),
+                    egui::FontId::default(),
+                    ui.visuals().text_color()
+                );
+            } else if subtick_step.is_some_and(|step| i % step == 0) {
+                ui.painter().line_segment(
+                    [pos2(x, ruler_y + KEYFRAME_SIZE * 0.75), pos2(x, ruler_y + KEYFRAME_SIZE)],
+                    Stroke::new(1.0, Color32::from_gray(70))
+                );
+            }
         }
 
         ui.painter().line_segment(
@@ -234,7 +543,7 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
                             Scene::default()
                                 .zoom_range(0.5..=0.5)
                                 .show(ui, &mut rect, |ui| {
-                                    cel.draw(&app.textures, ui, false);
+                                    cel.draw(&app.textures, &app.affine_matrices, &app.viewport.effects, ui, false);
                                 });
                         });
                     });
@@ -243,7 +552,23 @@ pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
                 pos += frame.duration as f32;
             }
         }
+
+        if let Some(start) = app.timeline.box_select_start {
+            let mouse_pos = ui.ctx().input(|i| i.pointer.latest_pos().unwrap_or(start));
+            let selection_rect = Rect::from_two_pos(start, mouse_pos);
+
+            ui.painter().rect_filled(selection_rect, 0, Color32::from_rgba_unmultiplied(97, 134, 255, 40));
+            ui.painter().rect_stroke(selection_rect, 0, Stroke::new(1.0, Color32::from_rgb(97, 134, 255)), egui::StrokeKind::Inside);
+        }
     });
+
+    let mut animation_end = 0;
+
+    if let Some(animation) = app.animations.get(app.animation_id) {
+        animation_end = animation.get_total_frames();
+    }
+
+    draw_scrollbar(ui, &mut app.timeline, animation_end);
     
     ui.add_space(ui.available_height());
 }
@@ -256,10 +581,10 @@ pub fn input(input: &InputState, app: &mut Yanimator) {
             egui::Event::MouseWheel { unit: _, delta, modifiers: _ } => {
                 if app.timeline.rect.contains(mouse_pos) {
                     if input.modifiers.ctrl {
-                        app.timeline.zoom += delta.y;
-
-                        if app.timeline.zoom < 3.0 {
-                            app.timeline.zoom = 3.0;
+                        if delta.y > 0.0 {
+                            zoom_at(&mut app.timeline, mouse_pos.x, ZOOM_SPEED);
+                        } else if delta.y < 0.0 {
+                            zoom_at(&mut app.timeline, mouse_pos.x, 1.0 / ZOOM_SPEED);
                         }
                     } else {
                         app.timeline.scroll += delta.y * SCROLL_SPEED;
@@ -276,6 +601,18 @@ pub fn input(input: &InputState, app: &mut Yanimator) {
         }
     }
 
+    let ruler_rect = Rect::from_min_max(
+        pos2(app.timeline.track_rect.min.x, app.timeline.track_rect.min.y - KEYFRAME_SIZE),
+        pos2(app.timeline.track_rect.max.x, app.timeline.track_rect.min.y)
+    );
+    let scrubbing = input.pointer.button_down(PointerButton::Primary) && input.pointer.press_origin().is_some_and(|pos| ruler_rect.contains(pos));
+
+    if scrubbing {
+        app.frames = x_to_frame(&app.timeline, mouse_pos.x).round().max(0.0) as usize;
+    }
+
+    let on_scrollbar = input.pointer.press_origin().is_some_and(|pos| get_scrollbar_rect(&app.timeline).contains(pos));
+
     let mut deselect_others = None;
     let mut any_hovered = false;
 
@@ -326,16 +663,58 @@ pub fn input(input: &InputState, app: &mut Yanimator) {
         app.timeline.dragging = false;
     }
 
-    if !any_hovered && app.timeline.dragging == false && (input.pointer.button_down(PointerButton::Primary) || input.pointer.button_down(PointerButton::Secondary)) {
+    if !any_hovered && app.timeline.dragging == false && !input.modifiers.shift && !scrubbing && !on_scrollbar && (input.pointer.button_down(PointerButton::Primary) || input.pointer.button_down(PointerButton::Secondary)) {
         for keyframe in &mut app.timeline.keyframes {
             keyframe.selected = false;
         }
     }
 
-    // todo: there will probably be more text box modal things
-    // there should be a common function to check for those
-    // when more are added
-    if app.animation_cells_panel.creation_modal_open {return;}
+    if !any_hovered && !app.timeline.dragging && input.pointer.button_pressed(PointerButton::Primary) && app.timeline.track_rect.contains(mouse_pos) {
+        app.timeline.box_select_start = Some(mouse_pos);
+    }
+
+    if let Some(start) = app.timeline.box_select_start {
+        if input.pointer.button_released(PointerButton::Primary) {
+            let selection_rect = Rect::from_two_pos(start, mouse_pos);
+
+            for keyframe in &mut app.timeline.keyframes {
+                if keyframe.input_rect.intersects(selection_rect) {
+                    keyframe.selected = true;
+                }
+            }
+
+            app.timeline.box_select_start = None;
+        }
+    }
+
+    if app.keyboard_captured {return;}
+
+    for event in &input.events {
+        match event {
+            egui::Event::Copy => copy_keyframes(app),
+            egui::Event::Cut => cut_keyframes(app),
+            // Text that isn't copied keyframes, like cel names or OAM words, doesn't paste anything
+            egui::Event::Paste(text) => {
+                if let Some(copied_frames) = read_clipboard_text(text) {
+                    app.timeline.clipboard = copied_frames;
+                    paste_keyframes(app);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    if input.modifiers.command && input.key_pressed(Key::A) {
+        select_all_keyframes(app);
+    }
+
+    if input.modifiers.command && input.key_pressed(Key::I) {
+        invert_keyframe_selection(app);
+    }
+
+    if input.key_pressed(Key::F) {
+        zoom_to_fit(app);
+    }
 
     if input.key_pressed(Key::Space) {
         app.timeline.playing = !app.timeline.playing;
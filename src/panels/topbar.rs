@@ -1,11 +1,17 @@
 use egui::{include_image, vec2, Button, Id, ImageButton, Modal, Ui};
 
-use crate::{anim_parser::Animation, AppState, Yanimator};
+use crate::{anim_parser::{get_unique_name, Animation}, AppState, Yanimator};
 
 pub struct Topbar {
     pub animation_creation_modal_open: bool,
     animation_deletion_modal_open: bool,
+    animation_rename_modal_open: bool,
+    animation_duplicate_modal_open: bool,
     deleting_anim: Option<String>,
+    renaming_anim: Option<String>,
+    duplicating_anim: Option<String>,
+    // Also give the duplicate its own copies of the cels it uses
+    duplicate_cels: bool,
     animation_name: String
 }
 
@@ -14,19 +20,31 @@ impl Topbar {
         Self {
             animation_creation_modal_open: false,
             animation_deletion_modal_open: false,
+            animation_rename_modal_open: false,
+            animation_duplicate_modal_open: false,
             deleting_anim: None,
+            renaming_anim: None,
+            duplicating_anim: None,
+            duplicate_cels: false,
             animation_name: String::new()
         }
     }
+
+    pub fn is_modal_open(&self) -> bool {
+        self.animation_creation_modal_open || self.animation_deletion_modal_open || self.animation_rename_modal_open || self.animation_duplicate_modal_open
+    }
 }
 
 fn is_anim_name_invalid(app: &mut Yanimator) -> Option<String> {
-    let anim_name = &app.topbar.animation_name;
-    
+    get_anim_name_error(app, &app.topbar.animation_name)
+}
+
+pub fn get_anim_name_error(app: &Yanimator, anim_name: &str) -> Option<String> {
     if anim_name.len() == 0 {return None}
     if anim_name.contains(" ") {return Some(String::from("Animation name must not contain spaces"))}
     if !anim_name.chars().nth(0).unwrap().is_alphabetic() {return Some(String::from("First character in animation name must be a letter"))}
-    if app.animations.iter().any(|animation| &animation.name == anim_name) {return Some(String::from("Animation name has already been used"))}
+    if !anim_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {return Some(String::from("Animation name must only contain letters, numbers and underscores"))}
+    if app.animations.iter().any(|animation| animation.name == anim_name) {return Some(String::from("Animation name has already been used"))}
 
     None
 }
@@ -42,6 +60,55 @@ fn create_animation(app: &mut Yanimator) {
     })
 }
 
+fn rename_animation(app: &mut Yanimator) {
+    app.topbar.animation_rename_modal_open = false;
+
+    if let Some(renaming_anim) = app.topbar.renaming_anim.take() {
+        if let Some(animation) = app.animations.iter_mut().find(|anim| anim.name == renaming_anim) {
+            animation.name = app.topbar.animation_name.clone();
+        }
+    }
+}
+
+fn duplicate_animation(app: &mut Yanimator) {
+    app.topbar.animation_duplicate_modal_open = false;
+
+    let duplicating_anim = match app.topbar.duplicating_anim.take() {
+        Some(name) => name,
+        None => return
+    };
+
+    let mut duplicate = match app.animations.iter().find(|anim| anim.name == duplicating_anim) {
+        Some(animation) => animation.clone(),
+        None => return
+    };
+
+    duplicate.name = app.topbar.animation_name.clone();
+
+    if app.topbar.duplicate_cels {
+        let used_cels: Vec<String> = duplicate.get_used_cels().into_iter().cloned().collect();
+
+        for cel_name in used_cels {
+            let mut cel = match app.animation_cels.get(&cel_name) {
+                Some(cel) => cel.clone(),
+                None => continue
+            };
+
+            cel.name = get_unique_name(&cel_name, |n| app.animation_cels.contains_key(n));
+
+            for frame in &mut duplicate.frames {
+                if frame.cell == cel_name {
+                    frame.cell = cel.name.clone();
+                }
+            }
+
+            app.animation_cels.insert(cel.name.clone(), cel);
+        }
+    }
+
+    app.animations.push(duplicate);
+}
+
 fn remove_animation(app: &mut Yanimator) {
     if let Some(deleting_anim) = &app.topbar.deleting_anim {
         let anim_pos = app.animations.iter().position(|anim| &anim.name == deleting_anim);
@@ -58,6 +125,7 @@ pub fn ui_animation_editor(ui: &mut Ui, app: &mut Yanimator) {
     .show(ui, |ui| {
         ui.horizontal(|ui| {
             let mut i = 0;
+            let all_anim_names: Vec<String> = app.animations.iter().map(|anim| anim.name.clone()).collect();
             
             for animation in &mut app.animations {
                 let button = ui.button(&animation.name);
@@ -70,6 +138,20 @@ pub fn ui_animation_editor(ui: &mut Ui, app: &mut Yanimator) {
                 }
 
                 button.context_menu(|ui| {
+                    if ui.add(Button::image_and_text(include_image!("../../assets/edit.png"), "Rename")).clicked() {
+                        app.topbar.animation_rename_modal_open = true;
+                        app.topbar.renaming_anim = Some(animation.name.clone());
+                        app.topbar.animation_name = animation.name.clone();
+                        ui.close_menu();
+                    }
+
+                    if ui.add(Button::image_and_text(include_image!("../../assets/add.png"), "Duplicate")).clicked() {
+                        app.topbar.animation_duplicate_modal_open = true;
+                        app.topbar.duplicating_anim = Some(animation.name.clone());
+                        app.topbar.animation_name = get_unique_name(&animation.name, |n| all_anim_names.iter().any(|name| name == n));
+                        ui.close_menu();
+                    }
+
                     if ui.add(Button::image_and_text(include_image!("../../assets/delete.png"), "Delete")).clicked() {
                         app.topbar.animation_deletion_modal_open = true;
                         app.topbar.deleting_anim = Some(animation.name.clone());
@@ -131,6 +213,58 @@ pub fn ui_animation_editor(ui: &mut Ui, app: &mut Yanimator) {
                 });
             }
         });
+    } else if app.topbar.animation_rename_modal_open {
+        Modal::new(Id::new("animation_rename")).show(ui.ctx(), |ui| {
+            if let Some(renaming_anim) = &app.topbar.renaming_anim {
+                ui.heading(format!("Renaming {}", renaming_anim));
+                ui.separator();
+
+                ui.label("New Animation Name:");
+
+                let field = ui.text_edit_singleline(&mut app.topbar.animation_name);
+
+                if let Some(message) = is_anim_name_invalid(app) {
+                    field.show_tooltip_text(message);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Cancel").clicked() {
+                        app.topbar.animation_rename_modal_open = false;
+                    }
+
+                    if ui.button("Rename").clicked() && !app.topbar.animation_name.is_empty() && is_anim_name_invalid(app).is_none() {
+                        rename_animation(app);
+                    }
+                });
+            }
+        });
+    } else if app.topbar.animation_duplicate_modal_open {
+        Modal::new(Id::new("animation_duplicate")).show(ui.ctx(), |ui| {
+            if let Some(duplicating_anim) = &app.topbar.duplicating_anim {
+                ui.heading(format!("Duplicating {}", duplicating_anim));
+                ui.separator();
+
+                ui.label("Animation Name:");
+
+                let field = ui.text_edit_singleline(&mut app.topbar.animation_name);
+
+                if let Some(message) = is_anim_name_invalid(app) {
+                    field.show_tooltip_text(message);
+                }
+
+                ui.checkbox(&mut app.topbar.duplicate_cels, "Duplicate cels used by the animation");
+
+                ui.horizontal(|ui| {
+                    if ui.button("Cancel").clicked() {
+                        app.topbar.animation_duplicate_modal_open = false;
+                    }
+
+                    if ui.button("Duplicate").clicked() && !app.topbar.animation_name.is_empty() && is_anim_name_invalid(app).is_none() {
+                        duplicate_animation(app);
+                    }
+                });
+            }
+        });
     }
 }
 
@@ -1,34 +1,563 @@
-use egui::{Rect, Scene, Ui};
-use image::flat::View;
+use std::path::PathBuf;
 
-use crate::{AppState, Yanimator};
+use egui::{include_image, pos2, Color32, ColorImage, Rect, Scene, Stroke, TextureHandle, Ui};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::{anim_parser::ObjEffects, palette_parser::Palette, sprite_parser::Spritesheet, tilemap_parser::{Tilemap, TilemapSize}, AppState, Yanimator};
+
+// Size of an 8x8 tile inside the scene, see OAM::draw
+const TILE_SIZE: f32 = 20.0;
+const PIXEL_SIZE: f32 = TILE_SIZE / 8.0;
+const CHECKERBOARD_SIZE: f32 = 16.0;
+const GBA_SCREEN_WIDTH: f32 = 240.0;
+const GBA_SCREEN_HEIGHT: f32 = 160.0;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ViewportBackground {
+    Default,
+    Checkerboard,
+    Solid
+}
+
+// Image shown under the cels to line them up with backgrounds, saved with the project
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ReferenceImage {
+    pub path: String,
+    pub x: f32,
+    pub y: f32,
+    pub scale: f32,
+    pub opacity: f32,
+    pub locked: bool,
+    pub visible: bool,
+    #[serde(skip)]
+    texture: Option<TextureHandle>,
+    #[serde(skip)]
+    load_failed: bool
+}
+
+impl ReferenceImage {
+    pub fn new(path: String) -> Self {
+        ReferenceImage {
+            path,
+            x: 0.0,
+            y: 0.0,
+            scale: 1.0,
+            opacity: 0.5,
+            locked: false,
+            visible: true,
+            texture: None,
+            load_failed: false
+        }
+    }
+
+    fn load_texture(&mut self, ui: &mut Ui) {
+        let image = match image::open(&self.path) {
+            Ok(image) => image.to_rgba8(),
+            Err(_) => {
+                self.load_failed = true;
+                return;
+            }
+        };
+
+        let size = [image.width() as usize, image.height() as usize];
+
+        self.texture = Some(ui.ctx().load_texture(
+            "reference_image",
+            ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice()),
+            egui::TextureOptions {
+                magnification: egui::TextureFilter::Nearest,
+                minification: egui::TextureFilter::Linear,
+                wrap_mode: egui::TextureWrapMode::ClampToEdge,
+                mipmap_mode: None,
+            }
+        ));
+    }
+}
+
+// A BG layer built from the game's own graphics, saved with the project
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Backdrop {
+    pub tileset_path: String,
+    pub tilemap_path: String,
+    pub palette_path: String,
+    pub size: TilemapSize,
+    // BG scroll registers
+    pub scroll_x: i32,
+    pub scroll_y: i32,
+    // Where the cel origin is on the screen
+    pub sprite_x: i32,
+    pub sprite_y: i32,
+    pub visible: bool,
+    #[serde(skip)]
+    texture: Option<TextureHandle>,
+    #[serde(skip)]
+    load_error: Option<String>
+}
+
+impl Backdrop {
+    pub fn new() -> Self {
+        Backdrop {
+            tileset_path: String::new(),
+            tilemap_path: String::new(),
+            palette_path: String::new(),
+            size: TilemapSize::Size32x32,
+            scroll_x: 0,
+            scroll_y: 0,
+            sprite_x: 120,
+            sprite_y: 80,
+            visible: true,
+            texture: None,
+            load_error: None
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.tileset_path.is_empty() && !self.tilemap_path.is_empty() && !self.palette_path.is_empty()
+    }
+
+    fn reload(&mut self) {
+        self.texture = None;
+        self.load_error = None;
+    }
+
+    fn load_texture(&mut self, ui: &mut Ui) {
+        let tileset = match Spritesheet::from_4bpp(&self.tileset_path) {
+            Ok(tileset) => tileset,
+            Err(err) => {
+                self.load_error = Some(format!("Couldn't load tileset: {}", err));
+                return;
+            }
+        };
+
+        let tilemap = match Tilemap::from_bin(&self.tilemap_path, self.size) {
+            Ok(tilemap) => tilemap,
+            Err(err) => {
+                self.load_error = Some(format!("Couldn't load tilemap: {}", err));
+                return;
+            }
+        };
+
+        let palette = match Palette::from_pal(&self.palette_path) {
+            Ok(palette) => palette,
+            Err(err) => {
+                self.load_error = Some(format!("Couldn't load palette: {}", err));
+                return;
+            }
+        };
+
+        self.texture = Some(ui.ctx().load_texture(
+            "backdrop",
+            tilemap.render(&tileset, &palette),
+            egui::TextureOptions {
+                magnification: egui::TextureFilter::Nearest,
+                minification: egui::TextureFilter::Nearest,
+                // BGs wrap around when scrolled past their edges
+                wrap_mode: egui::TextureWrapMode::Repeat,
+                mipmap_mode: None,
+            }
+        ));
+    }
+}
 
 pub struct Viewport {
     scene_rect: Rect,
-    pub selection_indicator_enabled: bool
+    pub selection_indicator_enabled: bool,
+    background: ViewportBackground,
+    background_color: Color32,
+    axis_lines_enabled: bool,
+    tile_grid_enabled: bool,
+    pub reference_image: Option<ReferenceImage>,
+    pub backdrop: Option<Backdrop>,
+    // Settings picked in the menu before a tilemap is loaded, so projects don't save an empty backdrop
+    backdrop_draft: Backdrop,
+    pub effects: ObjEffects,
+    // Where the mouse is in the cel editor, in pixels from the cel's origin
+    pub cel_mouse_pos: Option<(i32, i32)>
 }
 
 impl Viewport {
     pub fn init() -> Self {
         Viewport {
             scene_rect: Rect::ZERO,
-            selection_indicator_enabled: true
+            selection_indicator_enabled: true,
+            background: ViewportBackground::Default,
+            background_color: Color32::BLACK,
+            axis_lines_enabled: false,
+            tile_grid_enabled: false,
+            reference_image: None,
+            backdrop: None,
+            backdrop_draft: Backdrop::new(),
+            effects: ObjEffects::init(),
+            cel_mouse_pos: None
+        }
+    }
+
+    pub fn reset_view(&mut self) {
+        // Scene refits itself to its contents when given an invalid rect
+        self.scene_rect = Rect::ZERO;
+    }
+}
+
+fn draw_background(ui: &mut Ui, viewport: &Viewport) {
+    let rect = ui.available_rect_before_wrap();
+
+    match viewport.background {
+        ViewportBackground::Default => {},
+        ViewportBackground::Checkerboard => {
+            egui::Image::new(include_image!("../../assets/tiled_bg.png"))
+                .texture_options(egui::TextureOptions {
+                    magnification: egui::TextureFilter::Nearest,
+                    minification: egui::TextureFilter::Nearest,
+                    wrap_mode: egui::TextureWrapMode::Repeat,
+                    mipmap_mode: None,
+                })
+                .uv(Rect::from_min_max(pos2(0.0, 0.0), pos2(rect.width() / CHECKERBOARD_SIZE, rect.height() / CHECKERBOARD_SIZE)))
+                .paint_at(ui, rect);
+        },
+        ViewportBackground::Solid => {
+            ui.painter().rect_filled(rect, 0, viewport.background_color);
+        }
+    }
+}
+
+fn pick_file(filter_name: &str, extensions: &[&str], title: &str) -> Option<String> {
+    let file_path: PathBuf = FileDialog::new()
+    .add_filter(filter_name, extensions)
+    .set_directory("/")
+    .set_title(title)
+    .pick_file()?;
+
+    file_path.to_str().map(String::from)
+}
+
+fn load_reference_image(app: &mut Yanimator) {
+    if let Some(path) = pick_file("Image", &["png", "jpg", "jpeg"], "Select a reference image") {
+        app.viewport.reference_image = Some(ReferenceImage::new(path));
+    }
+}
+
+// Has to be called inside of the scene
+fn draw_backdrop(ui: &mut Ui, backdrop: &mut Option<Backdrop>) {
+    let backdrop = match backdrop {
+        Some(backdrop) => backdrop,
+        None => return
+    };
+
+    if !backdrop.visible || !backdrop.is_ready() {return;}
+
+    if backdrop.texture.is_none() && backdrop.load_error.is_none() {
+        backdrop.load_texture(ui);
+    }
+
+    if let Some(texture) = &backdrop.texture {
+        let map_size = texture.size_vec2();
+
+        // Only the part of the BG that would be visible on the GBA's screen
+        let screen_rect = Rect::from_min_size(
+            pos2(-backdrop.sprite_x as f32 * PIXEL_SIZE, -backdrop.sprite_y as f32 * PIXEL_SIZE),
+            egui::vec2(GBA_SCREEN_WIDTH, GBA_SCREEN_HEIGHT) * PIXEL_SIZE
+        );
+
+        let uv = Rect::from_min_size(
+            pos2(backdrop.scroll_x as f32 / map_size.x, backdrop.scroll_y as f32 / map_size.y),
+            egui::vec2(GBA_SCREEN_WIDTH / map_size.x, GBA_SCREEN_HEIGHT / map_size.y)
+        );
+
+        ui.painter().image(texture.id(), screen_rect, uv, Color32::WHITE);
+        ui.painter().rect_stroke(screen_rect, 0, Stroke::new(1.0, Color32::from_gray(100)), egui::StrokeKind::Outside);
+    }
+}
+
+fn ui_effects_menu(ui: &mut Ui, effects: &mut ObjEffects) {
+    egui::Grid::new("effects_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("EVA").on_hover_text("How much of semi-transparent OAMs shows, out of 16");
+            ui.add(egui::Slider::new(&mut effects.eva, 0..=16));
+            ui.end_row();
+
+            ui.label("EVB").on_hover_text("How much of what's behind semi-transparent OAMs shows, out of 16");
+            ui.add(egui::Slider::new(&mut effects.evb, 0..=16));
+            ui.end_row();
+
+            ui.label("Mosaic Size");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut effects.mosaic_width).speed(0.1).range(1..=16).prefix("w: "));
+                ui.add(egui::DragValue::new(&mut effects.mosaic_height).speed(0.1).range(1..=16).prefix("h: "));
+            });
+            ui.end_row();
+        });
+
+    ui.checkbox(&mut effects.obj_windows_visible, "Show OBJ Windows");
+}
+
+fn ui_backdrop_menu(ui: &mut Ui, app: &mut Yanimator) {
+    let backdrop = match &mut app.viewport.backdrop {
+        Some(backdrop) => backdrop,
+        None => &mut app.viewport.backdrop_draft
+    };
+
+    egui::Grid::new("backdrop_files_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Tileset");
+            if ui.button(if backdrop.tileset_path.is_empty() { "Select .4bpp..." } else { &backdrop.tileset_path }).clicked() {
+                if let Some(path) = pick_file("4bpp tileset", &["4bpp"], "Select a BG tileset") {
+                    backdrop.tileset_path = path;
+                    backdrop.reload();
+                }
+            }
+            ui.end_row();
+
+            ui.label("Tilemap");
+            if ui.button(if backdrop.tilemap_path.is_empty() { "Select .bin..." } else { &backdrop.tilemap_path }).clicked() {
+                if let Some(path) = pick_file("Tilemap", &["bin"], "Select a BG tilemap") {
+                    backdrop.tilemap_path = path;
+                    backdrop.reload();
+                }
+            }
+            ui.end_row();
+
+            ui.label("Palette");
+            if ui.button(if backdrop.palette_path.is_empty() { "Select .pal..." } else { &backdrop.palette_path }).clicked() {
+                if let Some(path) = pick_file("Palette", &["pal"], "Select a BG palette") {
+                    backdrop.palette_path = path;
+                    backdrop.reload();
+                }
+            }
+            ui.end_row();
+
+            ui.label("Map Size");
+            let previous_size = backdrop.size;
+            egui::ComboBox::from_id_salt("backdrop_size_dropdown")
+                .selected_text({
+                    let (width, height) = backdrop.size.get_width_and_height();
+                    format!("{}x{}", width, height)
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut backdrop.size, TilemapSize::Size32x32, "32x32");
+                    ui.selectable_value(&mut backdrop.size, TilemapSize::Size64x32, "64x32");
+                    ui.selectable_value(&mut backdrop.size, TilemapSize::Size32x64, "32x64");
+                    ui.selectable_value(&mut backdrop.size, TilemapSize::Size64x64, "64x64");
+                });
+            if backdrop.size != previous_size {
+                backdrop.reload();
+            }
+            ui.end_row();
+
+            ui.label("Scroll");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut backdrop.scroll_x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut backdrop.scroll_y).prefix("y: "));
+            });
+            ui.end_row();
+
+            ui.label("Sprite Position");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut backdrop.sprite_x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut backdrop.sprite_y).prefix("y: "));
+            });
+            ui.end_row();
+        });
+
+    if let Some(message) = &backdrop.load_error {
+        ui.colored_label(ui.visuals().error_fg_color, message);
+    }
+
+    ui.checkbox(&mut backdrop.visible, "Visible");
+
+    ui.separator();
+
+    let mut remove = false;
+    ui.horizontal(|ui| {
+        if ui.button("Reload").clicked() {
+            backdrop.reload();
+        }
+
+        if ui.button("Remove").clicked() {
+            remove = true;
+            ui.close_menu();
+        }
+    });
+
+    if remove {
+        app.viewport.backdrop = None;
+        app.viewport.backdrop_draft = Backdrop::new();
+    } else if app.viewport.backdrop.is_none() && !app.viewport.backdrop_draft.tilemap_path.is_empty() {
+        app.viewport.backdrop = Some(std::mem::replace(&mut app.viewport.backdrop_draft, Backdrop::new()));
+    }
+}
+
+// Has to be called inside of the scene
+fn draw_reference_image(ui: &mut Ui, reference_image: &mut Option<ReferenceImage>) {
+    let reference_image = match reference_image {
+        Some(reference_image) => reference_image,
+        None => return
+    };
+
+    if !reference_image.visible {return;}
+
+    if reference_image.texture.is_none() && !reference_image.load_failed {
+        reference_image.load_texture(ui);
+    }
+
+    if let Some(texture) = &reference_image.texture {
+        let rect = Rect::from_min_size(
+            pos2(reference_image.x * PIXEL_SIZE, reference_image.y * PIXEL_SIZE),
+            texture.size_vec2() * reference_image.scale * PIXEL_SIZE
+        );
+
+        ui.painter().image(
+            texture.id(),
+            rect,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::from_white_alpha((reference_image.opacity * 255.0) as u8)
+        );
+    }
+}
+
+fn ui_reference_image_menu(ui: &mut Ui, app: &mut Yanimator) {
+    if ui.button("Load Image...").clicked() {
+        load_reference_image(app);
+        ui.close_menu();
+    }
+
+    let reference_image = match &mut app.viewport.reference_image {
+        Some(reference_image) => reference_image,
+        None => return
+    };
+
+    if reference_image.load_failed {
+        ui.colored_label(ui.visuals().error_fg_color, format!("Couldn't load {}", reference_image.path));
+    } else {
+        ui.label(&reference_image.path);
+    }
+
+    ui.checkbox(&mut reference_image.visible, "Visible");
+    ui.checkbox(&mut reference_image.locked, "Locked");
+
+    ui.add_enabled_ui(!reference_image.locked, |ui| {
+        egui::Grid::new("reference_image_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("X");
+                ui.add(egui::DragValue::new(&mut reference_image.x).speed(0.5));
+                ui.end_row();
+
+                ui.label("Y");
+                ui.add(egui::DragValue::new(&mut reference_image.y).speed(0.5));
+                ui.end_row();
+
+                ui.label("Scale");
+                ui.add(egui::DragValue::new(&mut reference_image.scale).speed(0.01).range(0.01..=16.0));
+                ui.end_row();
+
+                ui.label("Opacity");
+                ui.add(egui::Slider::new(&mut reference_image.opacity, 0.0..=1.0));
+                ui.end_row();
+            });
+    });
+
+    ui.separator();
+
+    if ui.button("Remove").clicked() {
+        app.viewport.reference_image = None;
+        ui.close_menu();
+    }
+}
+
+// Has to be called inside of the scene
+fn draw_guides(ui: &mut Ui, axis_lines_enabled: bool, tile_grid_enabled: bool) {
+    let visible_rect = ui.clip_rect();
+    let scale = ui.ctx().layer_transform_to_global(ui.layer_id()).map_or(1.0, |transform| transform.scaling);
+
+    if tile_grid_enabled {
+        let stroke = Stroke::new(1.0 / scale, Color32::from_white_alpha(30));
+
+        let mut x = (visible_rect.min.x / TILE_SIZE).floor() * TILE_SIZE;
+        while x < visible_rect.max.x {
+            ui.painter().vline(x, visible_rect.y_range(), stroke);
+            x += TILE_SIZE;
+        }
+
+        let mut y = (visible_rect.min.y / TILE_SIZE).floor() * TILE_SIZE;
+        while y < visible_rect.max.y {
+            ui.painter().hline(visible_rect.x_range(), y, stroke);
+            y += TILE_SIZE;
         }
     }
+
+    if axis_lines_enabled {
+        ui.painter().vline(0.0, visible_rect.y_range(), Stroke::new(2.0 / scale, Color32::from_rgb(80, 200, 80)));
+        ui.painter().hline(visible_rect.x_range(), 0.0, Stroke::new(2.0 / scale, Color32::from_rgb(200, 80, 80)));
+    }
+}
+
+fn ui_toolbar(ui: &mut Ui, app: &mut Yanimator) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("viewport_background_dropdown")
+            .selected_text(match app.viewport.background {
+                ViewportBackground::Default => "Default Background",
+                ViewportBackground::Checkerboard => "Checkerboard",
+                ViewportBackground::Solid => "Solid Color"
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut app.viewport.background, ViewportBackground::Default, "Default Background");
+                ui.selectable_value(&mut app.viewport.background, ViewportBackground::Checkerboard, "Checkerboard");
+                ui.selectable_value(&mut app.viewport.background, ViewportBackground::Solid, "Solid Color");
+            });
+
+        if app.viewport.background == ViewportBackground::Solid {
+            ui.color_edit_button_srgba(&mut app.viewport.background_color);
+        }
+
+        ui.separator();
+
+        ui.checkbox(&mut app.viewport.axis_lines_enabled, "Axis Lines");
+        ui.checkbox(&mut app.viewport.tile_grid_enabled, "Tile Grid");
+
+        ui.separator();
+
+        ui.menu_button("Reference Image", |ui| {
+            ui_reference_image_menu(ui, app);
+        });
+
+        ui.menu_button("Backdrop", |ui| {
+            ui_backdrop_menu(ui, app);
+        });
+
+        ui.menu_button("Effects", |ui| {
+            ui_effects_menu(ui, &mut app.viewport.effects);
+        });
+
+        ui.separator();
+
+        if ui.button("Reset View").clicked() {
+            app.viewport.reset_view();
+        }
+    });
 }
 
 pub fn ui_animation_editor(ui: &mut Ui, app: &mut Yanimator) {
+    draw_background(ui, &app.viewport);
+    let (axis_lines_enabled, tile_grid_enabled) = (app.viewport.axis_lines_enabled, app.viewport.tile_grid_enabled);
+
     Scene::default()
     .zoom_range(0.1..=4.0)
     .show(ui, &mut app.viewport.scene_rect, |ui| {
     
     //let animation = &app.animations[app.animation_id];
+
+    draw_backdrop(ui, &mut app.viewport.backdrop);
+    draw_reference_image(ui, &mut app.viewport.reference_image);
+    draw_guides(ui, axis_lines_enabled, tile_grid_enabled);
     
     let animation = app.animations.get(app.animation_id);
     if let Some(animation) = animation {
         if let Some(frame) = animation.frames.get(animation.current_frame) {
             if let Some(animation_cel) = app.animation_cels.get(&frame.cell) {
-                animation_cel.draw(&app.textures, ui, false);
+                animation_cel.draw(&app.textures, &app.affine_matrices, &app.viewport.effects, ui, false);
             }
         }
         
@@ -39,28 +568,46 @@ pub fn ui_animation_editor(ui: &mut Ui, app: &mut Yanimator) {
 }
 
 pub fn ui_cell_editor(ui: &mut Ui, app: &mut Yanimator) {
+    draw_background(ui, &app.viewport);
+    let (axis_lines_enabled, tile_grid_enabled) = (app.viewport.axis_lines_enabled, app.viewport.tile_grid_enabled);
+    let viewport_rect = ui.max_rect();
+
     Scene::default()
     .zoom_range(0.1..=4.0)
     .show(ui, &mut app.viewport.scene_rect, |ui| {
 
     //let animation = &app.animations[app.animation_id];
 
+    draw_backdrop(ui, &mut app.viewport.backdrop);
+    draw_reference_image(ui, &mut app.viewport.reference_image);
+    draw_guides(ui, axis_lines_enabled, tile_grid_enabled);
+
+    // Tiles are drawn 20 points wide, so a pixel is 2.5 points in the scene
+    app.viewport.cel_mouse_pos = match (ui.ctx().pointer_hover_pos(), ui.ctx().layer_transform_from_global(ui.layer_id())) {
+        (Some(pointer), Some(transform)) if viewport_rect.contains(pointer) => {
+            let pos = transform * pointer;
+            Some(((pos.x / 2.5).floor() as i32, (pos.y / 2.5).floor() as i32))
+        },
+        _ => None
+    };
+
     if let Some(animation_cel) = app.animation_cels.get_mut(&app.editing_cell) {
-        let mut i = 0;
-        for oam in &mut animation_cel.oams {
-            oam.selected = app.editing_oam == i;
-            i += 1;
+        // The OAM being edited is always part of the selection
+        if let Some(oam) = animation_cel.oams.get_mut(app.editing_oam) {
+            oam.selected = true;
         }
         
-        animation_cel.draw(&app.textures, ui, app.viewport.selection_indicator_enabled);
+        animation_cel.draw(&app.textures, &app.affine_matrices, &app.viewport.effects, ui, app.viewport.selection_indicator_enabled);
     }
 
     });
 }
 
 pub fn ui(ui: &mut Ui, app: &mut Yanimator) {
+    ui_toolbar(ui, app);
+
     match &app.state {
         AppState::AnimationEditor => ui_animation_editor(ui, app),
         AppState::CellEditor => ui_cell_editor(ui, app),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::{anim_parser::{AnimationCel, OAMFlip}, oam_fitter::{self, TileAllocator}, sprite_parser::{Sprite, Spritesheet, MAX_TILE, SHEET_WIDTH}};
+
+// (tile, width, height) of a block of tiles used by an OAM
+type Block = (usize, usize, usize);
+
+pub struct OptimizedSheet {
+    pub spritesheet: Spritesheet,
+    // Where every used block ended up, and the flip needed to show it the same way
+    remaps: HashMap<Block, (usize, OAMFlip)>,
+    pub old_tile_count: usize,
+    pub unused_tiles: usize,
+    pub duplicate_blocks: usize,
+    pub flipped_blocks: usize,
+    // Set when the blocks didn't fit in the tiles OAMs can point at, the sheet is incomplete then
+    overflowed: bool
+}
+
+impl OptimizedSheet {
+    pub fn get_bytes_saved(&self) -> isize {
+        (self.old_tile_count as isize - self.spritesheet.sprites.len() as isize) * 0x20
+    }
+
+    // Why the result can't be applied, if it can't
+    pub fn get_apply_error(&self) -> Option<String> {
+        if self.overflowed {
+            return Some(format!("The optimized sheet doesn't fit in tiles 0x000 to 0x{:03x}", MAX_TILE));
+        }
+
+        // Blocks that overlapped in the old sheet get their own tiles, which can take up more room
+        if self.spritesheet.sprites.len() > self.old_tile_count {
+            return Some(String::from("The optimized sheet would be bigger than the current one"));
+        }
+
+        None
+    }
+
+    pub fn get_report(&self) -> String {
+        let mut report = String::from("Spritesheet optimization report, generated by Yanimator\n\n");
+
+        report.push_str(&format!("Tiles before: {}\n", self.old_tile_count));
+        report.push_str(&format!("Tiles after: {}\n", self.spritesheet.sprites.len()));
+        report.push_str(&format!("Unused tiles removed: {}\n", self.unused_tiles));
+        report.push_str(&format!("Blocks reused from identical tiles: {} ({} of them flipped)\n", self.duplicate_blocks, self.flipped_blocks));
+        report.push_str(&format!("Bytes saved: {}\n", self.get_bytes_saved()));
+
+        report
+    }
+
+    // Points every OAM at its block in the optimized sheet
+    pub fn remap_cels<'a>(&self, cels: impl Iterator<Item = &'a mut AnimationCel>) {
+        for cel in cels {
+            for oam in cel.oams.iter_mut() {
+                let (width, height) = oam.get_width_and_height();
+
+                if let Some(&(tile, flip)) = self.remaps.get(&(oam.tile, width, height)) {
+                    oam.tile = tile;
+                    oam.flip = combine_flips(oam.flip, flip);
+                }
+            }
+        }
+    }
+}
+
+fn combine_flips(a: OAMFlip, b: OAMFlip) -> OAMFlip {
+    let horizontal = matches!(a, OAMFlip::Horizontal | OAMFlip::Both) != matches!(b, OAMFlip::Horizontal | OAMFlip::Both);
+    let vertical = matches!(a, OAMFlip::Vertical | OAMFlip::Both) != matches!(b, OAMFlip::Vertical | OAMFlip::Both);
+
+    match (horizontal, vertical) {
+        (false, false) => OAMFlip::None,
+        (true, false) => OAMFlip::Horizontal,
+        (false, true) => OAMFlip::Vertical,
+        (true, true) => OAMFlip::Both
+    }
+}
+
+fn get_block_tiles(spritesheet: &Spritesheet, (tile, width, height): Block) -> Vec<Sprite> {
+    let mut tiles = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            tiles.push(spritesheet.sprites.get(tile + x + y * SHEET_WIDTH).cloned().unwrap_or_else(Sprite::blank));
+        }
+    }
+
+    tiles
+}
+
+// Builds a new sheet with only the tiles OAMs use, storing identical (or flipped) blocks once
+pub fn optimize<'a>(spritesheet: &Spritesheet, cels: impl Iterator<Item = &'a AnimationCel>) -> OptimizedSheet {
+    let mut blocks: Vec<Block> = Vec::new();
+    // Affine OAMs can't flip, so their blocks have to be stored as they are
+    let mut affine_blocks: Vec<Block> = Vec::new();
+
+    for cel in cels {
+        for oam in &cel.oams {
+            let (width, height) = oam.get_width_and_height();
+            let block = (oam.tile, width, height);
+
+            if !blocks.contains(&block) {
+                blocks.push(block);
+            }
+
+            if oam.affine && !affine_blocks.contains(&block) {
+                affine_blocks.push(block);
+            }
+        }
+    }
+
+    // Big blocks go first so smaller ones can be found inside of them
+    blocks.sort_by_key(|&(tile, width, height)| (usize::MAX - width * height, tile));
+
+    let mut used_tiles = vec![false; spritesheet.sprites.len()];
+    for &(tile, width, height) in &blocks {
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(used) = used_tiles.get_mut(tile + x + y * SHEET_WIDTH) {
+                    *used = true;
+                }
+            }
+        }
+    }
+
+    let mut optimized = Spritesheet { sprites: Vec::new() };
+    let mut allocator = TileAllocator::new(&optimized);
+    let mut remaps = HashMap::new();
+    let mut duplicate_blocks = 0;
+    let mut flipped_blocks = 0;
+    let mut overflowed = false;
+
+    for block in blocks {
+        let (_, width, height) = block;
+        let tiles = get_block_tiles(spritesheet, block);
+
+        let remap = match oam_fitter::find_block(&optimized, &allocator, &tiles, width, height, !affine_blocks.contains(&block)) {
+            Some((tile, flip)) => {
+                duplicate_blocks += 1;
+                if flip != OAMFlip::None {
+                    flipped_blocks += 1;
+                }
+
+                (tile, flip)
+            },
+            None => {
+                let tile = match allocator.allocate(&mut optimized, width, height) {
+                    Some(tile) => tile,
+                    None => {
+                        overflowed = true;
+                        break;
+                    }
+                };
+
+                for y in 0..height {
+                    for x in 0..width {
+                        optimized.sprites[tile + x + y * SHEET_WIDTH] = tiles[x + y * width].clone();
+                    }
+                }
+
+                (tile, OAMFlip::None)
+            }
+        };
+
+        remaps.insert(block, remap);
+    }
+
+    OptimizedSheet {
+        spritesheet: optimized,
+        remaps,
+        old_tile_count: spritesheet.sprites.len(),
+        unused_tiles: used_tiles.iter().filter(|&&used| !used).count(),
+        duplicate_blocks,
+        flipped_blocks,
+        overflowed
+    }
+}
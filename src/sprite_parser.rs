@@ -1,10 +1,18 @@
-use std::fs;
+use std::{fs, io::BufWriter};
+
+use crate::palette_parser::RGB;
+
+#[derive(Clone, PartialEq)]
 pub struct Sprite {
     pub pixels: Vec<u8>
 }
 
 const LOWER_NIBBLE_MASK: u8 = 0b_00001111_u8;
 const UPPER_NIBBLE_MASK: u8 = 0b_11110000_u8;
+// Tiles per row in the spritesheet
+pub const SHEET_WIDTH: usize = 32;
+// The highest tile an OAM can point at
+pub const MAX_TILE: usize = 0x3FF;
 
 impl Sprite {
     pub fn from_4bpp(bytes: &[u8]) -> Sprite {
@@ -23,8 +31,42 @@ impl Sprite {
             pixels
         }
     }
+
+    pub fn blank() -> Sprite {
+        Sprite {
+            pixels: vec![0; 0x40]
+        }
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.pixels.iter().all(|&pixel| pixel == 0)
+    }
+
+    pub fn flipped(&self, horizontal: bool, vertical: bool) -> Sprite {
+        let mut pixels = vec![0; 0x40];
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let source_x = if horizontal { 7 - x } else { x };
+                let source_y = if vertical { 7 - y } else { y };
+                pixels[x + y * 8] = self.pixels[source_x + source_y * 8];
+            }
+        }
+
+        Sprite {
+            pixels
+        }
+    }
+
+    pub fn to_4bpp(&self) -> Vec<u8> {
+        self.pixels
+            .chunks(2)
+            .map(|pair| (pair[0] & LOWER_NIBBLE_MASK) | ((pair[1] << 4) & UPPER_NIBBLE_MASK))
+            .collect()
+    }
 }
 
+#[derive(Clone)]
 pub struct Spritesheet {
     pub sprites: Vec<Sprite>
 }
@@ -45,4 +87,39 @@ impl Spritesheet {
             sprites
         })
     }
-}
\ No newline at end of file
+
+    pub fn to_4bpp(&self, file_path: &str) -> Result<(), std::io::Error> {
+        let bytes: Vec<u8> = self.sprites.iter().flat_map(|sprite| sprite.to_4bpp()).collect();
+
+        fs::write(file_path, bytes)
+    }
+
+    // Laid out 32 tiles wide like the game's graphics, with color 0 transparent
+    pub fn to_png(&self, file_path: &str, palette: &[RGB]) -> Result<(), png::EncodingError> {
+        let width = SHEET_WIDTH * 8;
+        let height = self.sprites.len().div_ceil(SHEET_WIDTH) * 8;
+        let mut indexes = vec![0u8; width * height];
+
+        for (i, sprite) in self.sprites.iter().enumerate() {
+            let tile_x = (i % SHEET_WIDTH) * 8;
+            let tile_y = (i / SHEET_WIDTH) * 8;
+
+            for (j, &pixel) in sprite.pixels.iter().enumerate() {
+                indexes[tile_x + j % 8 + (tile_y + j / 8) * width] = pixel;
+            }
+        }
+
+        // Two pixels per byte, the left pixel in the upper nibble
+        let data: Vec<u8> = indexes.chunks(2).map(|pair| (pair[0] << 4) | pair[1]).collect();
+
+        let file = fs::File::create(file_path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Four);
+        encoder.set_palette(palette.iter().flat_map(|color| [color.r, color.g, color.b]).collect::<Vec<u8>>());
+        encoder.set_trns(vec![0u8]);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)
+    }
+}
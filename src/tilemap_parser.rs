@@ -0,0 +1,116 @@
+use std::fs;
+
+use egui::ColorImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{palette_parser::Palette, sprite_parser::Spritesheet};
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum TilemapSize {
+    Size32x32,
+    Size64x32,
+    Size32x64,
+    Size64x64
+}
+
+impl TilemapSize {
+    pub fn get_width_and_height(&self) -> (usize, usize) {
+        match self {
+            TilemapSize::Size32x32 => (32, 32),
+            TilemapSize::Size64x32 => (64, 32),
+            TilemapSize::Size32x64 => (32, 64),
+            TilemapSize::Size64x64 => (64, 64)
+        }
+    }
+}
+
+pub struct TilemapEntry {
+    pub tile: usize,
+    pub horizontal_flip: bool,
+    pub vertical_flip: bool,
+    pub palette: usize
+}
+
+impl TilemapEntry {
+    pub fn new(entry: u16) -> TilemapEntry {
+        // 0xPVHT TTTT TTTT
+        TilemapEntry {
+            tile: (entry & 0x03FF) as usize,
+            horizontal_flip: entry & 0x0400 != 0,
+            vertical_flip: entry & 0x0800 != 0,
+            palette: (entry >> 12) as usize
+        }
+    }
+}
+
+pub struct Tilemap {
+    pub entries: Vec<TilemapEntry>,
+    pub width: usize,
+    pub height: usize
+}
+
+impl Tilemap {
+    pub fn from_bin(file_path: &str, size: TilemapSize) -> Result<Tilemap, std::io::Error> {
+        let bytes = fs::read(file_path)?;
+        let (width, height) = size.get_width_and_height();
+        let mut entries = Vec::new();
+
+        for y in 0..height {
+            for x in 0..width {
+                // Maps bigger than 32x32 are split into 32x32 screen blocks
+                let block = x / 32 + (y / 32) * (width / 32);
+                let index = (block * 0x400 + (y % 32) * 32 + x % 32) * 2;
+
+                let entry = match bytes.get(index..index + 2) {
+                    Some(entry) => (entry[0] as u16) | ((entry[1] as u16) << 8),
+                    None => 0
+                };
+
+                entries.push(TilemapEntry::new(entry));
+            }
+        }
+
+        Ok(Tilemap {
+            entries,
+            width,
+            height
+        })
+    }
+
+    // Color 0 is left transparent
+    pub fn render(&self, tileset: &Spritesheet, palette: &Palette) -> ColorImage {
+        let pixel_width = self.width * 8;
+        let pixel_height = self.height * 8;
+        let mut image = ColorImage::new([pixel_width, pixel_height], egui::Color32::TRANSPARENT);
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let tile = match tileset.sprites.get(entry.tile) {
+                Some(tile) => tile,
+                None => continue
+            };
+
+            let colors = match palette.palettes.get(entry.palette) {
+                Some(colors) => colors,
+                None => continue
+            };
+
+            let tile_x = (i % self.width) * 8;
+            let tile_y = (i / self.width) * 8;
+
+            for y in 0..8 {
+                for x in 0..8 {
+                    let source_x = if entry.horizontal_flip { 7 - x } else { x };
+                    let source_y = if entry.vertical_flip { 7 - y } else { y };
+                    let color_id = tile.pixels[source_x + source_y * 8] as usize;
+
+                    if color_id == 0 {continue;}
+
+                    let rgb = &colors[color_id];
+                    image[(tile_x + x, tile_y + y)] = egui::Color32::from_rgb(rgb.r, rgb.g, rgb.b);
+                }
+            }
+        }
+
+        image
+    }
+}